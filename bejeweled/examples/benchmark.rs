@@ -0,0 +1,45 @@
+//! Measures how fast bots can play: moves per second on seeded games, and how fast the board can be searched
+//! for matches and scoring swaps. Run it with `cargo run --release --example benchmark`.
+
+extern crate bejeweled;
+
+use std::time::Instant;
+
+use bejeweled::*;
+
+/// Runs `f` `times` times and returns how many runs per second that was
+fn per_second<F: FnMut()>(times: usize, mut f: F) -> f64 {
+	let start = Instant::now();
+	for _ in 0..times {
+		f();
+	}
+	times as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+	let configs = [
+		GameConfig::default(),
+		GameConfig { rows: 8, cols: 8, colors: 4 },
+		GameConfig { rows: 12, cols: 9, colors: 5 },
+	];
+	println!("{:<12} {:>10} {:>15} {:>15} {:>19}", "board", "moves/s", "legal_moves/s", "match_groups/s",
+			 "pieces_to_remove/s");
+	for config in &configs {
+		let mut moves = 0;
+		let mut games = (0..20).map(|seed| Game::with_config(*config, seed)).collect::<Vec<_>>();
+		// Every move plays one of the scoring swaps, picked by the move count so it's not always the first one
+		let moves_per_second = per_second(games.len() * 200, || {
+			let game = &mut games[moves % 20];
+			let legal = game.legal_moves();
+			let mov = &legal[(moves * 7) % legal.len()];
+			assert!(game.make_move(mov).made);
+			moves += 1;
+		});
+		let game = &games[0];
+		let legal_per_second = per_second(20_000, || assert!(!game.legal_moves().is_empty()));
+		let groups_per_second = per_second(20_000, || assert!(game.match_groups().is_empty()));
+		let pieces_per_second = per_second(20_000, || assert!(game.pieces_to_remove().is_empty()));
+		println!("{:<12} {:>10.0} {:>15.0} {:>15.0} {:>19.0}", format!("{}x{}, {}", config.rows, config.cols, config.colors),
+				 moves_per_second, legal_per_second, groups_per_second, pieces_per_second);
+	}
+}
@@ -0,0 +1,51 @@
+//! Looks ahead with `simulate_sampled`: plays an expectimax bot that samples the new pieces against a bot that always
+//! plays the first hint.
+
+extern crate bejeweled;
+extern crate rand;
+
+use bejeweled::*;
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+/// The best total score `depth` moves can be expected to make, averaged over `samples` ways the new pieces
+/// could come in after every move
+fn expectimax<R: Rng>(game: &Game, depth: u32, samples: u32, rng: &mut R) -> f64 {
+	if depth == 0 || game.is_over() {
+		return 0.0;
+	}
+	game.legal_moves().iter().map(|mov| value(game, mov, depth, samples, rng)).fold(0.0, f64::max)
+}
+
+fn value<R: Rng>(game: &Game, mov: &Move, depth: u32, samples: u32, rng: &mut R) -> f64 {
+	let total = (0..samples).map(|_| match game.simulate_sampled(mov, rng) {
+		Some((next, outcome)) => outcome.score() as f64 + expectimax(&next, depth - 1, samples, rng),
+		None => 0.0,
+	}).sum::<f64>();
+	total / samples as f64
+}
+
+/// Plays a game of `moves` moves, `pick` chooses every move
+fn play<F: FnMut(&Game) -> Move>(seed: u32, moves: i32, mut pick: F) -> i32 {
+	let mut game = Game::with_seed(seed);
+	game.limit = Limit::Moves(moves);
+	while !game.is_over() {
+		let mov = pick(&game);
+		assert!(game.make_move(&mov).made);
+	}
+	game.score
+}
+
+fn main() {
+	let (games, moves) = (10, 20);
+	let mut rng = XorShiftRng::from_seed([7, 1, 2, 3]);
+	let first = (0..games).map(|seed| play(seed, moves, |game| game.hint().unwrap())).sum::<i32>();
+	let lookahead = (0..games).map(|seed| play(seed, moves, |game| {
+		let legal = game.legal_moves();
+		let values = legal.iter().map(|mov| value(game, mov, 2, 2, &mut rng)).collect::<Vec<_>>();
+		let best = values.iter().cloned().fold(0.0, f64::max);
+		legal[values.iter().position(|&v| v == best).unwrap()].clone()
+	})).sum::<i32>();
+	println!("Average score over {} moves: {} playing the first hint, {} looking 2 moves ahead",
+			 moves, first / games as i32, lookahead / games as i32);
+	assert!(lookahead > first, "looking ahead didn't help");
+}
@@ -0,0 +1,317 @@
+//! Bitboards for boards of up to 64 cells, like the classic 8x8 one: a `u64` for every color with a bit set for
+//! every gem of that color, row by row from the top left. Finding matches and scoring swaps is then a few shifts
+//! and ands per color instead of walking the grid.
+//! A game keeps its bitboard next to the grid and changes both together, so the bitboard is only built from
+//! scratch for a whole new board. Pieces falling into the gaps is done with shifts on the bitboard too, the grid
+//! only gets the moves of the pieces that actually fall.
+
+use {Cell, Gem, Grid, Move, Special};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Bitboard {
+	/// Gems of every color, in the order of `Color::ALL`. Hypercubes aren't in any of them, they never match
+	colors: [u64; 6],
+	/// The color of every cell, as an index in `Color::ALL`
+	color_of: [u8; 64],
+	hypercubes: u64,
+	rows: usize,
+	cols: usize,
+	/// Cells where a row of 3 can start without running off the end of the row
+	row_starts: u64,
+	/// Cells in the first column
+	first_col: u64,
+}
+
+impl Bitboard {
+	/// The bitboard of a grid, `None` if it has more than 64 cells
+	pub fn new(grid: &Grid) -> Option<Bitboard> {
+		let (rows, cols) = (grid.len(), grid[0].len());
+		if rows * cols > 64 {
+			return None;
+		}
+		let mut board = Bitboard { colors: [0; 6], color_of: [0; 64], hypercubes: 0, rows, cols, row_starts: 0, first_col: 0 };
+		for (r, row) in grid.iter().enumerate() {
+			for (c, gem) in row.iter().enumerate() {
+				let bit = board.bit((r, c));
+				board.set((r, c), gem);
+				if c + 2 < cols {
+					board.row_starts |= bit;
+				}
+				if c == 0 {
+					board.first_col |= bit;
+				}
+			}
+		}
+		Some(board)
+	}
+
+	pub fn bit(&self, cell: Cell) -> u64 {
+		1 << (cell.0 * self.cols + cell.1)
+	}
+
+	fn cell(&self, bit: u32) -> Cell {
+		(bit as usize / self.cols, bit as usize % self.cols)
+	}
+
+	/// The color masks and the hypercubes
+	fn masks(&mut self) -> impl Iterator<Item = &mut u64> {
+		self.colors.iter_mut().chain(Some(&mut self.hypercubes))
+	}
+
+	/// Puts a gem on a cell, replacing whatever was there
+	pub fn set(&mut self, cell: Cell, gem: &Gem) {
+		let bit = self.bit(cell);
+		self.color_of[cell.0 * self.cols + cell.1] = gem.color as u8;
+		for gems in self.masks() {
+			*gems &= !bit;
+		}
+		if gem.special == Special::Hypercube {
+			self.hypercubes |= bit;
+		} else {
+			self.colors[gem.color as usize] |= bit;
+		}
+	}
+
+	/// Swaps the gems on two cells
+	pub fn swap(&mut self, a: Cell, b: Cell) {
+		let both = self.bit(a) | self.bit(b);
+		self.color_of.swap(a.0 * self.cols + a.1, b.0 * self.cols + b.1);
+		for gems in self.masks() {
+			// Only a mask with one of the two in it changes
+			if (*gems & both).count_ones() == 1 {
+				*gems ^= both;
+			}
+		}
+	}
+
+	/// Takes the gems off the `holes` and lets the gems above them fall down, which leaves the holes at the top of
+	/// the columns. Returns which gems fell from where to where, top down
+	pub fn fall(&mut self, holes: u64) -> Vec<(Cell, Cell)> {
+		let cols = self.cols;
+		// A gem falls one row for every hole below it in its column
+		let mut above = 0;
+		let mut below = holes;
+		while below != 0 {
+			below >>= cols;
+			above |= below;
+		}
+		let mut falls = Vec::new();
+		let mut falling = above & !holes;
+		while falling != 0 {
+			let bit = falling.trailing_zeros();
+			falling &= falling - 1;
+			let (r, c) = self.cell(bit);
+			let drop = (holes & !0 << bit << 1 & self.first_col << c).count_ones() as usize;
+			falls.push(((r, c), (r + drop, c)));
+		}
+		// Bottom up, so every gem falls into a spot that's already empty
+		for &((r, c), (to, _)) in falls.iter().rev() {
+			self.color_of[to * cols + c] = self.color_of[r * cols + c];
+		}
+		for gems in self.masks() {
+			*gems &= !holes;
+		}
+		// Every gem right above a hole drops into it, over and over until the holes have all bubbled up to the top
+		let mut holes = holes;
+		loop {
+			let drop = holes >> cols & !holes;
+			if drop == 0 {
+				return falls;
+			}
+			for gems in self.masks() {
+				*gems = *gems & !drop | (*gems & drop) << cols;
+			}
+			holes = holes & !(drop << cols) | drop;
+		}
+	}
+
+	/// Every cell that's in a line of 3 or more
+	pub fn matches(&self) -> u64 {
+		self.colors.iter().fold(0, |cells, &gems| cells | self.in_rows(gems) | self.in_cols(gems))
+	}
+
+	/// The gems that are in a row of 3 or more of `gems`
+	fn in_rows(&self, gems: u64) -> u64 {
+		let starts = gems & gems >> 1 & gems >> 2 & self.row_starts;
+		starts | starts << 1 | starts << 2
+	}
+
+	/// The gems that are in a column of 3 or more of `gems`
+	fn in_cols(&self, gems: u64) -> u64 {
+		let c = self.cols;
+		let starts = gems & gems >> c & gems >> (2 * c);
+		starts | starts << c | starts << (2 * c)
+	}
+
+	/// The first `limit` swaps that would score, row by row, like `Game::legal_moves` finds them
+	pub fn scoring_swaps(&self, limit: usize) -> Vec<Move> {
+		let mut moves = Vec::new();
+		for i in 0..self.rows * self.cols {
+			let (r, c) = (i / self.cols, i % self.cols);
+			for &(j, right) in &[(i + 1, true), (i + self.cols, false)] {
+				if right && c + 1 == self.cols || !right && r + 1 == self.rows {
+					continue;
+				}
+				let swapped = 1 << i | 1 << j;
+				if self.hypercubes & swapped != 0 || self.swap_scores(i, j, swapped) {
+					let (row2, col2) = if right { (r, c + 1) } else { (r + 1, c) };
+					moves.push(Move { row1: r, col1: c, row2, col2 });
+					if moves.len() == limit {
+						return moves;
+					}
+				}
+			}
+		}
+		moves
+	}
+
+	/// Whether swapping the gems at bits `i` and `j` lines either of them up, neither of them a hypercube
+	fn swap_scores(&self, i: usize, j: usize, swapped: u64) -> bool {
+		let (color_i, color_j) = (self.color_of[i] as usize, self.color_of[j] as usize);
+		let (gems_i, gems_j) = if color_i == color_j {
+			(self.colors[color_i], self.colors[color_j])
+		} else {
+			(self.colors[color_i] ^ swapped, self.colors[color_j] ^ swapped)
+		};
+		(self.in_rows(gems_i) | self.in_cols(gems_i) | self.in_rows(gems_j) | self.in_cols(gems_j)) & swapped != 0
+	}
+
+	/// Every line of 3 or more, first the ones in rows top down and then the ones in columns left to right,
+	/// just like `lines` finds them on a grid
+	pub fn lines(&self) -> Vec<Vec<Cell>> {
+		let (mut rows, mut cols) = (Vec::new(), Vec::new());
+		for &gems in &self.colors {
+			let in_rows = self.in_rows(gems);
+			// Bits at the start of a column have the last cell of the row above them on their left
+			let mut starts = in_rows & !(in_rows << 1 & !self.first_col);
+			while starts != 0 {
+				let (r, c) = self.cell(starts.trailing_zeros());
+				starts &= starts - 1;
+				let length = (c..self.cols).take_while(|&c| in_rows & self.bit((r, c)) != 0).count();
+				rows.push((c..c + length).map(|c| (r, c)).collect::<Vec<_>>());
+			}
+			let in_cols = self.in_cols(gems);
+			let mut starts = in_cols & !(in_cols << self.cols);
+			while starts != 0 {
+				let (r, c) = self.cell(starts.trailing_zeros());
+				starts &= starts - 1;
+				let length = (r..self.rows).take_while(|&r| in_cols & self.bit((r, c)) != 0).count();
+				cols.push((r..r + length).map(|r| (r, c)).collect::<Vec<_>>());
+			}
+		}
+		rows.sort();
+		cols.sort_by_key(|line| (line[0].1, line[0].0));
+		rows.extend(cols);
+		rows
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use game_rng::seeded_rng;
+	use rand::Rng;
+	use std::collections::BTreeSet;
+	use {in_line, lines, random_grid, Color, Game, GameConfig};
+
+	const CONFIGS: [GameConfig; 4] = [
+		GameConfig { rows: 8, cols: 8, colors: 3 },
+		GameConfig { rows: 4, cols: 16, colors: 3 },
+		GameConfig { rows: 7, cols: 9, colors: 4 },
+		GameConfig { rows: 3, cols: 3, colors: 3 },
+	];
+
+	/// Random grids full of matches with about one hypercube in ten, and the config they go with
+	fn grids() -> Vec<(GameConfig, Grid)> {
+		CONFIGS.iter().flat_map(|config| (0..100).map(move |seed| {
+			let mut rng = seeded_rng(seed);
+			let mut grid = random_grid(config, &mut rng);
+			for gem in grid.iter_mut().flat_map(|row| row.iter_mut()) {
+				if rng.gen_range(0, 10) == 0 {
+					gem.special = Special::Hypercube;
+				}
+			}
+			(*config, grid)
+		})).collect()
+	}
+
+	fn cells(config: &GameConfig) -> Vec<Cell> {
+		(0..config.rows).flat_map(|r| (0..config.cols).map(move |c| (r, c))).collect()
+	}
+
+	#[test]
+	fn matches_agree_with_the_grid() {
+		for (config, grid) in grids() {
+			let board = Bitboard::new(&grid).unwrap();
+			let matches = board.matches();
+			for cell in cells(&config) {
+				assert_eq!(matches & board.bit(cell) != 0, in_line(&grid, cell), "{:?} on {:?}", cell, grid);
+			}
+		}
+	}
+
+	#[test]
+	fn lines_agree_with_the_grid() {
+		let mut found = 0;
+		for (_, grid) in grids() {
+			let expected = lines(&grid);
+			assert_eq!(Bitboard::new(&grid).unwrap().lines(), expected, "{:?}", grid);
+			found += expected.len();
+		}
+		assert!(found > 1000, "only {} lines, the grids don't have enough matches", found);
+	}
+
+	#[test]
+	fn scoring_swaps_agree_with_the_grid() {
+		for (config, grid) in grids() {
+			let board = Bitboard::new(&grid).unwrap();
+			let mut game = Game::with_config(config, 0);
+			game.set_grid(grid);
+			let expected = game.scoring_swaps_on_grid();
+			assert_eq!(board.scoring_swaps(usize::MAX), expected);
+			assert_eq!(board.scoring_swaps(3), expected[..expected.len().min(3)].to_vec());
+		}
+	}
+
+	#[test]
+	fn falling_agrees_with_the_grid() {
+		for (i, (config, grid)) in grids().into_iter().enumerate() {
+			let mut rng = seeded_rng(i as u32);
+			let holes = cells(&config).into_iter().filter(|_| rng.gen_range(0, 4) == 0).collect::<BTreeSet<_>>();
+			let mut board = Bitboard::new(&grid).unwrap();
+			let mut game = Game::with_config(config, 0);
+			game.set_grid(grid);
+			let mut expected = game.fall_on_grid(&holes);
+			expected.sort();
+			assert_eq!(board.fall(holes.iter().fold(0, |bits, &cell| bits | board.bit(cell))), expected);
+			// The holes are at the top now, filled the same way both boards have to be the same
+			for c in 0..config.cols {
+				for r in 0..holes.iter().filter(|cell| cell.1 == c).count() {
+					game.grid[r][c] = Gem::new(Color::Red);
+					board.set((r, c), &Gem::new(Color::Red));
+				}
+			}
+			assert!(Bitboard::new(&game.grid) == Some(board));
+		}
+	}
+
+	#[test]
+	fn games_play_the_same_without_a_bitboard() {
+		for config in CONFIGS.iter() {
+			for seed in 0..10 {
+				let mut game = Game::with_config(*config, seed);
+				let mut on_grid = game.clone();
+				for i in 0..60 {
+					// Reshuffling makes a new bitboard, so take it away before every move
+					on_grid.board = None;
+					let moves = game.legal_moves();
+					assert_eq!(moves, on_grid.legal_moves());
+					let mov = &moves[(i * 7 + seed as usize) % moves.len()];
+					assert_eq!(game.make_move(mov), on_grid.make_move(mov));
+					assert!(game.grid == on_grid.grid && game.score == on_grid.score);
+					assert!(game.board == Bitboard::new(&game.grid), "the bitboard doesn't match the grid anymore");
+				}
+			}
+		}
+	}
+}
@@ -1,20 +1,41 @@
 extern crate rand;
 extern crate time;
 extern crate ansi_term;
+extern crate game_rng;
+
+mod bitboard;
 
 use rand::{Rand, Rng};
 use std::fmt;
 use std::cmp;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, VecDeque};
+use std::hash::Hash;
+use std::thread;
+use std::time::Duration;
+use ansi_term::Colour;
 use ansi_term::Colour::{Blue, Green, Yellow, Purple, Red, White};
+use bitboard::Bitboard;
+use game_rng::GameRng;
 
 static SCORE_3: i32 = 10;
 static SCORE_4: i32 = 20;
 static SCORE_5: i32 = 30;
+static SCORE_L: i32 = 40;
+static SCORE_T: i32 = 50;
+static SCORE_CROSS: i32 = 60;
+/// Added to every group cleared in a cascade, once for every step of the cascade
 static FOLLOWUP_BONUS: i32 = 5;
+/// Score for every gem blown up by a special gem, on top of the ones in the match
+static DETONATION_SCORE: i32 = 5;
+/// How long a timed game lasts
+static TIME_LIMIT: Duration = Duration::from_secs(60);
+/// How long every move takes, so playing as fast as possible doesn't win every game
+static MOVE_TIME: Duration = Duration::from_millis(50);
+/// Reshuffling a dead board tries this many shuffles of its pieces before it gives up and uses new pieces
+static MAX_SHUFFLES: u32 = 100;
 
 /// Colors available to be placed on the game board
-#[derive(PartialEq, Eq, Debug, Clone, Hash)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
 pub enum Color {
 	Blue,
 	Green,
@@ -26,74 +47,129 @@ pub enum Color {
 
 impl fmt::Display for Color {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		let c = match *self {
-			Color::Blue => Blue.paint("\u{2588}"),
-			Color::Green => Green.paint("\u{2588}"),
-			Color::Orange => Yellow.paint("\u{2588}"),
-			Color::Purple => Purple.paint("\u{2588}"),
-			Color::Red => Red.paint("\u{2588}"),
-			Color::White => White.paint("\u{2588}"),
-		};
-		write!(f, "{}", c)
+		write!(f, "{}", self.colour().paint("\u{2588}"))
+	}
+}
+
+impl Color {
+	fn colour(&self) -> Colour {
+		match *self {
+			Color::Blue => Blue,
+			Color::Green => Green,
+			Color::Orange => Yellow,
+			Color::Purple => Purple,
+			Color::Red => Red,
+			Color::White => White,
+		}
+	}
+
+	/// Every color, in the order games with fewer colors pick them in
+	pub const ALL: [Color; 6] = [Color::Blue, Color::Green, Color::Orange, Color::Purple, Color::Red, Color::White];
+
+	/// One of the first `colors` colors, picked at random
+	pub fn random<R: Rng>(rng: &mut R, colors: usize) -> Color {
+		rng.choose(&Color::ALL[..colors]).unwrap_or(&Color::Blue).to_owned()
 	}
 }
 
 impl Rand for Color {
 	fn rand<R: Rng>(rng: &mut R) -> Self {
-		let colors = [Color::Blue,
-					  Color::Green,
-					  Color::Orange,
-					  Color::Purple,
-					  Color::Red,
-					  Color::White];
-		rng.choose(&colors).unwrap_or(&Color::Blue).to_owned()
+		Color::random(rng, Color::ALL.len())
 	}
 }
 
-/// A grid is just a matrix of colors, the colors are fully owned, this could be optimized by using references
-type Grid = Vec<Vec<Color>>;
+/// What a gem does when it's cleared
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Special {
+	/// Nothing, just a gem
+	None,
+	/// Made by matching 4 in a line, clears its whole row and column
+	Line,
+	/// Made by an L or T shaped match, clears the 3x3 square around it
+	Bomb,
+	/// Made by matching 5 in a line. It has no color of its own and never matches, but swapping it with
+	/// a gem clears every gem of that gem's color. Blown up by another special it clears a random color
+	Hypercube,
+}
 
-/// Create a random grid of colors (this will usually include some "invalid" states for a board to display)
-pub fn random_grid() -> Grid {
-	let mut rng = rand::thread_rng();
-	let mut grid = Vec::new();
-	for _ in 0..8 {
-		let mut row = Vec::new();
-		for _ in 0..8 {
-			row.push(rng.gen());
-		}
-		grid.push(row.to_owned())
-	}
-	grid
+/// A piece on the board
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub struct Gem {
+	pub color: Color,
+	pub special: Special,
 }
 
-/// A cell is a pair of indexes to the grid matrix. Assumptions are made throughout the code that the usize's will be >= 0 and <= 7
-pub type Cell = (usize, usize);
+impl Gem {
+	/// A plain gem
+	pub fn new(color: Color) -> Gem {
+		Gem { color, special: Special::None }
+	}
 
-/// Return the vertical neighbours (the rows above and below)
-fn row_neighbours(cell: &Cell) -> Vec<Cell> {
-	let mut cells = Vec::new();
-	if cell.0 < 7 {
-		cells.push((cell.0 + 1, cell.1));
+	/// Whether the two gems count as the same color when lining them up, hypercubes never match
+	pub fn matches(&self, other: &Gem) -> bool {
+		self.special != Special::Hypercube && other.special != Special::Hypercube && self.color == other.color
 	}
-	if cell.0 > 0 {
-		cells.push((cell.0 - 1, cell.1));
+}
+
+impl From<Color> for Gem {
+	fn from(color: Color) -> Gem {
+		Gem::new(color)
 	}
-	cells
 }
 
-/// Return the horizontal neighbours (the cols to the left and right)
-fn col_neighbours(cell: &Cell) -> Vec<Cell> {
-	let mut cells = Vec::new();
-	if cell.1 < 7 {
-		cells.push((cell.0, cell.1 + 1));
+impl fmt::Display for Gem {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.special {
+			Special::None => write!(f, "{}", self.color),
+			Special::Line => write!(f, "{}", self.color.colour().paint("+")),
+			Special::Bomb => write!(f, "{}", self.color.colour().paint("*")),
+			Special::Hypercube => write!(f, "{}", White.bold().paint("@")),
+		}
+	}
+}
+
+/// Settings for the board, the default is the classic 8x8 board with all six colors
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GameConfig {
+	/// At least 3
+	pub rows: usize,
+	/// At least 3
+	pub cols: usize,
+	/// How many of the colors in `Color::ALL` are used, from 3 to 6. Fewer colors make for more matches
+	pub colors: usize,
+}
+
+impl Default for GameConfig {
+	fn default() -> GameConfig {
+		GameConfig {
+			rows: 8,
+			cols: 8,
+			colors: Color::ALL.len(),
+		}
 	}
-	if cell.1 > 0 {
-		cells.push((cell.0, cell.1 - 1));
+}
+
+/// A grid is just a matrix of gems. Games on boards of up to 64 cells keep a bitboard of it too, which finds matches
+/// a lot faster
+pub type Grid = Vec<Vec<Gem>>;
+
+/// Create a random grid of plain gems (this will usually include some "invalid" states for a board to display)
+pub fn random_grid<R: Rng>(config: &GameConfig, rng: &mut R) -> Grid {
+	let mut grid = Vec::new();
+	for _ in 0..config.rows {
+		let mut row = Vec::new();
+		for _ in 0..config.cols {
+			row.push(Gem::new(Color::random(rng, config.colors)));
+		}
+		grid.push(row.to_owned())
 	}
-	cells
+	grid
 }
 
+/// A cell is a pair of indexes to the grid matrix, row first. Assumptions are made throughout the code
+/// that they're within the size of the board
+pub type Cell = (usize, usize);
+
 /// A move is swapping two pieces at coordinates row1, col1, row2, col2
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Move {
@@ -105,8 +181,9 @@ pub struct Move {
 
 impl Move {
 	/// A move is valid if it concerns two neighbouring cells and don't go off the grid
-	pub fn is_valid(&self) -> bool {
-		if self.row1 < 8 && self.col1 < 8 && self.row2 < 8 && self.col2 < 8 &&
+	pub fn is_valid(&self, config: &GameConfig) -> bool {
+		let (rows, cols) = (config.rows, config.cols);
+		if self.row1 < rows && self.col1 < cols && self.row2 < rows && self.col2 < cols &&
 		   (self.row1 == self.row2 &&
 			cmp::max(self.col1, self.col2) - cmp::min(self.col1, self.col2) == 1 ||
 			cmp::max(self.row1, self.row2) - cmp::min(self.row1, self.row2) == 1 &&
@@ -117,6 +194,281 @@ impl Move {
 	}
 }
 
+/// Where a game gets the time from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Clock {
+	/// The wall clock, started at `start` (in ns, from `time::precise_time_ns`). Every move waits a little
+	/// to stop players from spamming moves, the game is more or less endless so without some rate limiting
+	/// the winning strategy is always to play as fast as possible.
+	Real { start: u64 },
+	/// Time only passes when moves are made, every move takes `tick`. Much faster than the real clock
+	/// for bots that play a lot of games.
+	Simulated { tick: Duration, elapsed: Duration },
+}
+
+impl Clock {
+	/// A wall clock starting now
+	pub fn real() -> Clock {
+		Clock::Real { start: time::precise_time_ns() }
+	}
+
+	/// A simulated clock starting at 0
+	pub fn simulated(tick: Duration) -> Clock {
+		Clock::Simulated { tick, elapsed: Duration::from_secs(0) }
+	}
+
+	/// Time since the clock started
+	pub fn elapsed(&self) -> Duration {
+		match *self {
+			Clock::Real { start } => Duration::from_nanos(time::precise_time_ns().saturating_sub(start)),
+			Clock::Simulated { elapsed, .. } => elapsed,
+		}
+	}
+
+	/// Lets the time of a move pass
+	fn tick(&mut self) {
+		match *self {
+			Clock::Real { .. } => thread::sleep(MOVE_TIME),
+			Clock::Simulated { tick, ref mut elapsed } => *elapsed += tick,
+		}
+	}
+}
+
+/// When a game is over
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Limit {
+	/// The game goes on forever
+	None,
+	/// The game is over once this much time has passed on its clock
+	Time(Duration),
+	/// The game is over after this many moves
+	Moves(i32),
+}
+
+/// Swap the places of two gems on a grid
+fn swap(grid: &mut Grid, mov: &Move) {
+	let moved = grid[mov.row1][mov.col1];
+	grid[mov.row1][mov.col1] = grid[mov.row2][mov.col2];
+	grid[mov.row2][mov.col2] = moved;
+}
+
+/// Whether a cell is part of three or more of the same color in a row or column
+fn in_line(grid: &Grid, cell: Cell) -> bool {
+	// Number of cells of the same color next to this one, going in a direction
+	let (rows, cols) = (grid.len() as isize, grid[0].len() as isize);
+	let same = |dr: isize, dc: isize| {
+		let (mut r, mut c) = (cell.0 as isize + dr, cell.1 as isize + dc);
+		let mut length = 0;
+		while (0..rows).contains(&r) && (0..cols).contains(&c) && grid[r as usize][c as usize].matches(&grid[cell.0][cell.1]) {
+			length += 1;
+			r += dr;
+			c += dc;
+		}
+		length
+	};
+	same(-1, 0) + same(1, 0) >= 2 || same(0, -1) + same(0, 1) >= 2
+}
+
+/// The shape of a group of matching gems
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Shape {
+	Line3,
+	Line4,
+	/// 5 or more in a line
+	Line5,
+	/// A row and a column meeting at their ends
+	L,
+	/// A row or column ending in the middle of the other
+	T,
+	/// A row and a column crossing in both their middles
+	Cross,
+}
+
+impl Shape {
+	/// Points for clearing a group of this shape with the swap itself, before any cascades
+	pub fn score(&self) -> i32 {
+		match *self {
+			Shape::Line3 => SCORE_3,
+			Shape::Line4 => SCORE_4,
+			Shape::Line5 => SCORE_5,
+			Shape::L => SCORE_L,
+			Shape::T => SCORE_T,
+			Shape::Cross => SCORE_CROSS,
+		}
+	}
+}
+
+/// Three or more gems of the same color in a row or column, or several of those sharing gems,
+/// like an L or T shape
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MatchGroup {
+	pub color: Color,
+	pub shape: Shape,
+	pub cells: BTreeSet<Cell>,
+	/// Every line of three or more in the group
+	lines: Vec<Vec<Cell>>,
+}
+
+impl MatchGroup {
+	/// Points for clearing the group `depth` steps into a cascade, 0 being the swap itself.
+	/// Every step multiplies the score of the shape and adds `FOLLOWUP_BONUS`
+	pub fn score(&self, depth: u32) -> i32 {
+		let depth = depth as i32;
+		self.shape.score() * (depth + 1) + FOLLOWUP_BONUS * depth
+	}
+
+	/// Length of the longest line in the group
+	fn longest(&self) -> usize {
+		self.lines.iter().map(|line| line.len()).max().unwrap_or(0)
+	}
+
+	/// A cell where a row and a column of the group meet
+	fn crossing(&self) -> Option<Cell> {
+		self.crossings().next().map(|(cell, _, _)| cell)
+	}
+
+	/// Every cell where a row and a column of the group meet, with the row and the column
+	fn crossings<'a>(&'a self) -> Box<dyn Iterator<Item = (Cell, &'a Vec<Cell>, &'a Vec<Cell>)> + 'a> {
+		let row = |line: &Vec<Cell>| line[0].0 == line[1].0;
+		Box::new(self.lines.iter().filter(move |a| row(a)).flat_map(move |a| {
+			self.lines.iter().filter(move |b| !row(b))
+				.flat_map(move |b| a.iter().filter(move |c| b.contains(c)).map(move |&c| (c, a, b)))
+		}))
+	}
+
+	/// The line shape for a single line, otherwise the most valuable shape any row and column in it make
+	fn find_shape(&self) -> Shape {
+		let line = match self.longest() {
+			3 => Shape::Line3,
+			4 => Shape::Line4,
+			_ => Shape::Line5,
+		};
+		let middle = |line: &Vec<Cell>, cell: Cell| line[0] != cell && line[line.len() - 1] != cell;
+		self.crossings().map(|(cell, row, col)| match (middle(row, cell), middle(col, cell)) {
+			(false, false) => Shape::L,
+			(true, true) => Shape::Cross,
+			_ => Shape::T,
+		}).max().unwrap_or(line)
+	}
+}
+
+/// Every line of 3 or more matching gems, first the ones in rows top down and then the ones in columns left to right.
+/// This walks the whole grid, for boards too big for a bitboard
+fn lines(grid: &Grid) -> Vec<Vec<Cell>> {
+	let (rows, cols) = (grid.len(), grid[0].len());
+	let mut lines = Vec::new();
+	// Every run of matching gems, first in the rows and then in the columns
+	for &(outer, inner, horizontal) in &[(rows, cols, true), (cols, rows, false)] {
+		for i in 0..outer {
+			let cell = |j: usize| if horizontal { (i, j) } else { (j, i) };
+			let mut start = 0;
+			for j in 1..inner + 1 {
+				let (a, b) = (cell(start), cell(j.min(inner - 1)));
+				if j == inner || !grid[a.0][a.1].matches(&grid[b.0][b.1]) {
+					if j - start >= 3 {
+						lines.push((start..j).map(&cell).collect::<Vec<_>>());
+					}
+					start = j;
+				}
+			}
+		}
+	}
+	lines
+}
+
+/// The groups the `lines` of matching gems on the grid make, in order of their top left gem
+fn match_groups(grid: &Grid, lines: Vec<Vec<Cell>>) -> Vec<MatchGroup> {
+	let mut groups: Vec<MatchGroup> = Vec::new();
+	for line in lines {
+		let (sharing, mut rest): (Vec<_>, Vec<_>) = groups.into_iter()
+			.partition(|group| line.iter().any(|cell| group.cells.contains(cell)));
+		let mut group = MatchGroup {
+			color: grid[line[0].0][line[0].1].color,
+			shape: Shape::Line3,
+			cells: line.iter().cloned().collect(),
+			lines: Vec::new(),
+		};
+		for other in sharing {
+			group.lines.extend(other.lines);
+			group.cells.extend(other.cells);
+		}
+		group.lines.push(line);
+		rest.push(group);
+		groups = rest;
+	}
+	for group in &mut groups {
+		group.shape = group.find_shape();
+	}
+	groups.sort_by_key(|group| group.cells.iter().next().cloned());
+	groups
+}
+
+/// What happened in one step of a cascade, every step clears the matches on the board and lets pieces fall
+/// into the gaps, which can make new matches for the next step
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CascadeStep {
+	/// 0 for the matches made by the swap itself, 1 for the ones made by the pieces falling after that and so on
+	pub depth: u32,
+	/// The groups that matched, with what each one scored
+	pub groups: Vec<(MatchGroup, i32)>,
+	/// Special gems that went off, where they were. A swapped hypercube goes first
+	pub detonated: Vec<(Cell, Special)>,
+	/// Special gems made by the matches, they stay on the board while the rest of the match is cleared
+	pub created: Vec<(Cell, Gem)>,
+	/// Every cell that was cleared, matched or blown up, top down
+	pub cleared: Vec<Cell>,
+	/// Pieces that fell into the gaps, from where to where
+	pub falls: Vec<(Cell, Cell)>,
+	/// New pieces that came in at the top of the columns, where they ended up
+	pub spawned: Vec<(Cell, Color)>,
+	/// Everything the step scored, blown up gems included
+	pub score: i32,
+}
+
+/// What a move did to the board
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MoveOutcome {
+	/// Whether the move was made at all, otherwise the board is just like before
+	pub made: bool,
+	/// Every step of the cascade, in order
+	pub steps: Vec<CascadeStep>,
+}
+
+impl MoveOutcome {
+	/// What the move scored in total
+	pub fn score(&self) -> i32 {
+		self.steps.iter().map(|step| step.score).sum()
+	}
+
+	/// How many steps the cascade took, 0 if the move wasn't made
+	pub fn depth(&self) -> usize {
+		self.steps.len()
+	}
+}
+
+/// The cells a special gem at `cell` clears when it goes off, besides itself
+fn blast(grid: &Grid, cell: Cell, special: Special, color: &Color) -> Vec<Cell> {
+	let (rows, cols) = (grid.len(), grid[0].len());
+	match special {
+		Special::None => Vec::new(),
+		Special::Line => (0..cols).map(|c| (cell.0, c)).chain((0..rows).map(|r| (r, cell.1))).collect(),
+		Special::Bomb => {
+			let near = |x: usize, size: usize| x.saturating_sub(1)..(x + 2).min(size);
+			near(cell.0, rows).flat_map(|r| near(cell.1, cols).map(move |c| (r, c))).collect()
+		}
+		Special::Hypercube => (0..rows).flat_map(|r| (0..cols).map(move |c| (r, c)))
+			.filter(|&(r, c)| grid[r][c].special != Special::Hypercube && grid[r][c].color == *color)
+			.collect(),
+	}
+}
+
+/// Something that happened on the board besides pieces being cleared
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Event {
+	/// There were no scoring swaps left, so the pieces were shuffled
+	Reshuffled,
+}
+
 /// A game is the collection of stats about the game and its current grid
 #[derive(PartialEq, Eq, Clone, Hash)]
 pub struct Game {
@@ -124,153 +476,817 @@ pub struct Game {
 	pub moves: i32,
 	/// The current score of the game
 	pub score: i32,
-	/// 8x8 grid of colors
-	pub grid: Grid,
-	/// Start time, unix timestamp in ns
-	pub start_time: u64,
-	/// Defines whether or not the game has a time-limit
-	pub time_limit: bool,
+	/// Grid of gems, `config().rows` rows of `config().cols` gems
+	grid: Grid,
+	/// The same gems as `grid`, changed along with it. `None` on boards of more than 64 cells
+	board: Option<Bitboard>,
+	/// The time-limit is measured on this clock, a new clock starts the time over
+	pub clock: Clock,
+	/// Defines when the game is over
+	pub limit: Limit,
+	/// What happened during the last move, or while setting up the board before the first one
+	pub events: Vec<Event>,
+	config: GameConfig,
+	rng: GameRng,
 }
 
 impl Game {
-	/// Create a new game, creates a random board then ensures that it's in a valid state before returning
+	/// Create a new game on the real clock, optionally with a time-limit of 60 seconds.
+	/// Creates a random board then ensures that it's in a valid state before returning
 	pub fn new(time_limit: bool) -> Game {
+		let limit = if time_limit { Limit::Time(TIME_LIMIT) } else { Limit::None };
+		Game::with_rng(GameConfig::default(), Clock::real(), limit, GameRng::new(None))
+	}
+
+	/// Create a game without a limit where the board and every new piece come from `seed`,
+	/// so the same seed and the same moves always play out exactly the same.
+	/// It runs on a simulated clock where every move takes as long as on the real clock but without waiting,
+	/// so a 60 second time-limit still means at most 1200 moves
+	pub fn with_seed(seed: u32) -> Game {
+		Game::with_config(GameConfig::default(), seed)
+	}
+
+	/// Create a game like `with_seed` on a board of a different size or with fewer colors
+	pub fn with_config(config: GameConfig, seed: u32) -> Game {
+		Game::with_rng(config, Clock::simulated(MOVE_TIME), Limit::None, GameRng::new(Some(seed)))
+	}
+
+	/// Create a game like `with_seed` but starting from `grid` instead of a random board. The board is as big
+	/// as the grid and new pieces can have any color up to the last one of `Color::ALL` on it, but at least 3,
+	/// not counting hypercubes.
+	/// Any matches on it are cleared first and the new pieces come from `seed`, and if it has no scoring swaps
+	/// it's reshuffled
+	pub fn from_grid(grid: Grid, seed: u32) -> Game {
+		let colors = grid.iter().flat_map(|row| row.iter())
+			.filter(|gem| gem.special != Special::Hypercube)
+			.map(|gem| Color::ALL.iter().position(|c| *c == gem.color).unwrap() + 1)
+			.max().unwrap_or(0);
+		let config = GameConfig { rows: grid.len(), cols: grid.first().map_or(0, |row| row.len()), colors: colors.max(3) };
+		assert!(grid.iter().all(|row| row.len() == config.cols), "All rows of the grid need to be as long");
+		Game::with_grid(config, grid, Clock::simulated(MOVE_TIME), Limit::None, GameRng::new(Some(seed)))
+	}
+
+	fn with_rng(config: GameConfig, clock: Clock, limit: Limit, mut rng: GameRng) -> Game {
+		let grid = random_grid(&config, &mut rng.0);
+		Game::with_grid(config, grid, clock, limit, rng)
+	}
+
+	fn with_grid(config: GameConfig, grid: Grid, clock: Clock, limit: Limit, rng: GameRng) -> Game {
+		assert!(config.rows >= 3 && config.cols >= 3, "The board needs at least 3 rows and 3 columns");
+		assert!(config.colors >= 3 && config.colors <= Color::ALL.len(), "A game has between 3 and 6 colors");
 		let mut game = Game {
 			moves: 0,
 			score: 0,
-			grid: random_grid(),
-			start_time: 0,
-			time_limit: time_limit,
+			board: Bitboard::new(&grid),
+			grid,
+			clock,
+			limit,
+			events: Vec::new(),
+			config,
+			rng,
 		};
-		game.clear_board(true);
-		game.score = 0;
-		game.moves = 0;
-		game.start_time = time::precise_time_ns();
+		game.settle();
 		game
 	}
 
+	/// The size of the board and the number of colors
+	pub fn config(&self) -> &GameConfig {
+		&self.config
+	}
+
+	/// Grid of gems, `config().rows` rows of `config().cols` gems
+	pub fn grid(&self) -> &Grid {
+		&self.grid
+	}
+
+	/// Replaces the whole grid, which has to be as big as the board. Nothing is cleared or reshuffled,
+	/// so the grid can have matches or no scoring swaps left
+	pub fn set_grid(&mut self, grid: Grid) {
+		assert!(grid.len() == self.config.rows && grid.iter().all(|row| row.len() == self.config.cols),
+				"The grid has to be as big as the board");
+		self.board = Bitboard::new(&grid);
+		self.grid = grid;
+	}
+
+	/// Puts a gem on a cell of the grid, replacing whatever was there
+	pub fn set_gem(&mut self, (r, c): Cell, gem: Gem) {
+		self.grid[r][c] = gem;
+		if let Some(ref mut board) = self.board {
+			board.set((r, c), &gem);
+		}
+	}
+
+	/// Gets a new board ready to play: clears the matches that are already on it, without scoring them or making
+	/// special gems, and reshuffles if nothing scores
+	fn settle(&mut self) {
+		loop {
+			let cells = self.pieces_to_remove();
+			if cells.is_empty() {
+				break;
+			}
+			self.clear_cells(cells.into_iter().collect(), Vec::new());
+		}
+		if self.scoring_swaps(1).is_empty() {
+			self.reshuffle();
+		}
+	}
+
+	/// Shuffles the pieces until there are no matches on the board but at least one scoring swap.
+	/// Some sets of pieces can't be arranged like that, for example when they're nearly all the same color,
+	/// so after a while new pieces are used instead
+	fn reshuffle(&mut self) {
+		let mut pieces = self.grid.iter().flat_map(|row| row.iter().cloned()).collect::<Vec<_>>();
+		let cols = self.config.cols;
+		let mut shuffles = 0;
+		loop {
+			if shuffles < MAX_SHUFFLES {
+				self.rng.0.shuffle(&mut pieces);
+				self.grid = pieces.chunks(self.config.cols).map(|row| row.to_vec()).collect();
+				shuffles += 1;
+			} else {
+				self.grid = random_grid(&self.config, &mut self.rng.0);
+			}
+			// A whole new board, so a whole new bitboard
+			self.board = Bitboard::new(&self.grid);
+			let settled = match self.board {
+				Some(ref board) => board.matches() == 0,
+				None => (0..pieces.len()).all(|i| !in_line(&self.grid, (i / cols, i % cols))),
+			};
+			if settled && !self.scoring_swaps(1).is_empty() {
+				break;
+			}
+		}
+		self.events.push(Event::Reshuffled);
+	}
+
+	/// Whether the time is out or all moves have been made
+	pub fn is_over(&self) -> bool {
+		match self.limit {
+			Limit::None => false,
+			Limit::Time(limit) => self.clock.elapsed() > limit,
+			Limit::Moves(limit) => self.moves >= limit,
+		}
+	}
+
 	/// A little helper function to pretty-print the board
 	pub fn print_board(&self) {
-		println!("-----------------");
-		println!("{:<8}{:>9}", &self.moves, &self.score);
-		println!("-----------------");
+		let line = "-".repeat(2 * self.config.cols + 1);
+		println!("{}", line);
+		println!("{:<left$}{:>right$}", &self.moves, &self.score, left = self.config.cols, right = self.config.cols + 1);
+		println!("{}", line);
 		for r in &self.grid {
 			for c in r {
 				print!("|{}", c);
 			}
 			println!("|");
 		}
-		println!("-----------------");
+		println!("{}", line);
 	}
 
 	/// The top level function to make a move on the board,
-	/// only executed if the move is valid, and "undone" if it doesn't score.
-	/// Will return true if a move was made or false if the move could not be made,
-	/// either because it was invalid or because the time is out
-	pub fn make_move(&mut self, mov: &Move) -> bool {
-		// Sleep for a little while here to prevent spamming moves, the game is more or less endless
-		// so without some rate limiting the winning strategy is always to play as fast as possible.
-		std::thread::sleep(std::time::Duration::from_millis(50));
-		let now = time::precise_time_ns();
-		if mov.is_valid() && self.start_time + 60 * 1e9 as u64 >= now {
+	/// only executed if the move is valid, and "undone" if it doesn't score. Swapping a hypercube always scores.
+	/// The outcome says whether the move was made, it can't be if it's invalid or the game is over,
+	/// and what happened on the board if it was
+	pub fn make_move(&mut self, mov: &Move) -> MoveOutcome {
+		self.clock.tick();
+		self.play(mov)
+	}
+
+	/// What would happen if `mov` was made, without touching this game: the game after the move and what happened
+	/// during it, or `None` if the move can't be made or doesn't score. Nothing waits for the real clock, and
+	/// simulated clocks move on like in `make_move`.
+	/// The new pieces are exactly the ones `make_move` would bring in, so a bot that isn't supposed to know them
+	/// should use `simulate_sampled`
+	pub fn simulate(&self, mov: &Move) -> Option<(Game, MoveOutcome)> {
+		self.clone().simulated(mov)
+	}
+
+	/// Like `simulate` but the new pieces come from `rng` instead of the game, every call is one of the ways the move
+	/// could turn out. Averaging the score over a few calls estimates what a move is worth, which is what the chance
+	/// nodes of an expectimax search need, listing every way the pieces could come in quickly gets out of hand with
+	/// 6 colors for every cleared cell. The game that comes out keeps drawing new pieces from a seed taken from `rng`,
+	/// so searching deeper samples those too
+	pub fn simulate_sampled<R: Rng>(&self, mov: &Move, rng: &mut R) -> Option<(Game, MoveOutcome)> {
+		let mut game = self.clone();
+		game.rng = GameRng::new(Some(rng.next_u32()));
+		game.simulated(mov)
+	}
+
+	fn simulated(mut self, mov: &Move) -> Option<(Game, MoveOutcome)> {
+		if let Clock::Simulated { .. } = self.clock {
+			self.clock.tick();
+		}
+		let outcome = self.play(mov);
+		if outcome.made { Some((self, outcome)) } else { None }
+	}
+
+	/// `make_move` without the time passing
+	fn play(&mut self, mov: &Move) -> MoveOutcome {
+		self.events.clear();
+		let mut outcome = MoveOutcome::default();
+		if mov.is_valid(&self.config) && !self.is_over() {
 			self.moves += 1;
-			self.execute_move(mov);
-			if self.clear_board(true) == 0 {
+			let cube = |cell: Cell| self.grid[cell.0][cell.1].special == Special::Hypercube;
+			if cube((mov.row1, mov.col1)) || cube((mov.row2, mov.col2)) {
+				outcome.steps = self.activate_hypercube(mov);
+			} else {
 				self.execute_move(mov);
-				return false;
+				outcome.steps = self.clear_board(0, Some(mov));
+				if outcome.steps.is_empty() {
+					self.execute_move(mov);
+					return outcome;
+				}
 			}
-			true
-		} else {
-			false
+			outcome.made = true;
+			if self.scoring_swaps(1).is_empty() {
+				self.reshuffle();
+			}
+		}
+		outcome
+	}
+
+	/// Clearing the board means removing any pieces that are in a line of 3 or more, moving pieces above down one step
+	/// and adding a new piece at the top for each removes, over and over until nothing matches anymore.
+	/// Every group scores on its own, starting `depth` steps into the cascade. Matches of 4 or more make special gems,
+	/// on the moved gem if it's in the match, and special gems in a match go off
+	fn clear_board(&mut self, mut depth: u32, mut swapped: Option<&Move>) -> Vec<CascadeStep> {
+		let mut steps = Vec::new();
+		loop {
+			let groups = self.match_groups();
+			if groups.is_empty() {
+				return steps;
+			}
+			let created = groups.iter().filter_map(|group| self.special_for(group, swapped)).collect();
+			let cells = groups.iter().flat_map(|group| group.cells.iter().cloned()).collect();
+			let (mut step, blasted) = self.clear_cells(cells, created);
+			step.depth = depth;
+			step.groups = groups.into_iter().map(|group| {
+				let score = group.score(depth);
+				(group, score)
+			}).collect();
+			step.score = step.groups.iter().map(|&(_, score)| score).sum::<i32>() + DETONATION_SCORE * blasted as i32;
+			self.score += step.score;
+			steps.push(step);
+			depth += 1;
+			swapped = None;
 		}
 	}
 
-	/// Clearing the board means removing any pieces that are in a contiguous area > 3, moving pieces above down one step
-	/// and adding a new piece at the top for each removes
-	fn clear_board(&mut self, first_loop: bool) -> i32 {
-		let xs = self.pieces_to_remove();
-		let bonus = if first_loop { 0 } else { FOLLOWUP_BONUS };
-		let score = match xs.len() {
-			x if x < 3 => return 0,
-			3 => SCORE_3 + bonus,
-			4 => SCORE_4 + bonus,
-			_ => SCORE_5 + bonus,
+	/// The special gem a group makes and where it goes, `None` for a plain match of 3
+	fn special_for(&self, group: &MatchGroup, swapped: Option<&Move>) -> Option<(Cell, Gem)> {
+		let crossing = group.crossing();
+		let special = if group.longest() >= 5 {
+			Special::Hypercube
+		} else if crossing.is_some() {
+			Special::Bomb
+		} else if group.longest() == 4 {
+			Special::Line
+		} else {
+			return None;
 		};
-		self.score += score;
-		let mut rng = rand::thread_rng();
-		for x in xs {
-			for i in 0..x.0 {
-				self.execute_move(&Move {
-									  row1: x.0 - 1 - i,
-									  col1: x.1,
-									  row2: x.0 - i,
-									  col2: x.1,
-								  });
+		let moved = swapped.and_then(|mov| {
+			[(mov.row2, mov.col2), (mov.row1, mov.col1)].iter().cloned().find(|cell| group.cells.contains(cell))
+		});
+		let line = &group.lines[0];
+		let cell = moved.or(crossing).unwrap_or(line[line.len() / 2]);
+		// Special gems in the match go off instead of being turned into the new one
+		let plain = |&(r, c): &Cell| self.grid[r][c].special == Special::None;
+		let cell = if plain(&cell) { cell } else { group.cells.iter().cloned().find(plain)? };
+		Some((cell, Gem { color: group.color, special }))
+	}
+
+	/// Sets off a hypercube swapped with the gem next to it, which clears every gem of that gem's color.
+	/// Two hypercubes swapped together clear the whole board
+	fn activate_hypercube(&mut self, mov: &Move) -> Vec<CascadeStep> {
+		let (a, b) = ((mov.row1, mov.col1), (mov.row2, mov.col2));
+		let (cube, other) = if self.grid[a.0][a.1].special == Special::Hypercube { (a, b) } else { (b, a) };
+		let mut cells = if self.grid[other.0][other.1].special == Special::Hypercube {
+			let plain = Gem::new(self.grid[other.0][other.1].color);
+			self.set_gem(other, plain);
+			(0..self.config.rows).flat_map(|r| (0..self.config.cols).map(move |c| (r, c))).collect()
+		} else {
+			let color = self.grid[other.0][other.1].color;
+			blast(&self.grid, cube, Special::Hypercube, &color).into_iter().collect::<BTreeSet<_>>()
+		};
+		cells.insert(cube);
+		// It's used up, so it doesn't go off again while it's cleared
+		let plain = Gem::new(self.grid[cube.0][cube.1].color);
+		self.set_gem(cube, plain);
+		let (mut step, _) = self.clear_cells(cells, Vec::new());
+		step.detonated.insert(0, (cube, Special::Hypercube));
+		step.score = DETONATION_SCORE * step.cleared.len() as i32;
+		self.score += step.score;
+		let mut steps = vec![step];
+		steps.extend(self.clear_board(1, None));
+		steps
+	}
+
+	/// Clears `cells` and everything the special gems among them blow up, except where the `created` gems go.
+	/// Pieces above fall down and new pieces come in at the top. Returns what happened, without the groups or score,
+	/// and how many gems were blown up on top of `cells`
+	fn clear_cells(&mut self, mut cells: BTreeSet<Cell>, created: Vec<(Cell, Gem)>) -> (CascadeStep, usize) {
+		let mut step = CascadeStep::default();
+		for &(cell, _) in &created {
+			cells.remove(&cell);
+		}
+		let matched = cells.len();
+		// Special gems set each other off, always in the same order so hypercubes pick the same random colors
+		let mut fuses = cells.iter().cloned().filter(|&(r, c)| self.grid[r][c].special != Special::None).collect::<VecDeque<_>>();
+		while let Some(fuse) = fuses.pop_front() {
+			let gem = self.grid[fuse.0][fuse.1];
+			step.detonated.push((fuse, gem.special));
+			let color = match gem.special {
+				Special::Hypercube => Color::random(&mut self.rng.0, self.config.colors),
+				_ => gem.color,
+			};
+			for hit in blast(&self.grid, fuse, gem.special, &color) {
+				if !created.iter().any(|&(cell, _)| cell == hit) && cells.insert(hit) &&
+				   self.grid[hit.0][hit.1].special != Special::None {
+					fuses.push_back(hit);
+				}
 			}
-			self.grid[0][x.1] = rng.gen();
 		}
-		score + self.clear_board(false)
+		for &(cell, gem) in &created {
+			self.set_gem(cell, gem);
+		}
+		step.created = created;
+		// New pieces are picked top down, row by row, the first one picked for a column ends up lowest in it
+		let mut new = vec![Vec::new(); self.config.cols];
+		for &(_, c) in &cells {
+			new[c].push(Color::random(&mut self.rng.0, self.config.colors));
+		}
+		step.falls = match self.board {
+			Some(ref mut board) => {
+				let falls = board.fall(cells.iter().fold(0, |holes, &cell| holes | board.bit(cell)));
+				// Bottom up, so every piece falls into a spot that's already empty
+				for &((r, c), (to, _)) in falls.iter().rev() {
+					self.grid[to][c] = self.grid[r][c];
+				}
+				falls
+			}
+			None => self.fall_on_grid(&cells),
+		};
+		for (c, new) in new.into_iter().enumerate() {
+			for (r, color) in (0..new.len()).rev().zip(new) {
+				self.set_gem((r, c), Gem::new(color));
+				step.spawned.push(((r, c), color));
+			}
+		}
+		step.falls.sort();
+		step.spawned.sort_by_key(|&((r, c), _)| (c, r));
+		let blasted = cells.len() - matched;
+		step.cleared = cells.into_iter().collect();
+		(step, blasted)
+	}
+
+	/// Lets the pieces above the cleared `cells` fall into the gaps, for boards too big for a bitboard.
+	/// Returns which pieces fell from where to where
+	fn fall_on_grid(&mut self, cells: &BTreeSet<Cell>) -> Vec<(Cell, Cell)> {
+		let (rows, cols) = (self.config.rows, self.config.cols);
+		let mut falls = Vec::new();
+		for c in 0..cols {
+			let mut to = rows;
+			for r in (0..rows).rev() {
+				if !cells.contains(&(r, c)) {
+					to -= 1;
+					if to != r {
+						self.grid[to][c] = self.grid[r][c];
+						falls.push(((r, c), (to, c)));
+					}
+				}
+			}
+		}
+		falls
+	}
+
+	/// All the swaps that would score, row by row, swaps with a hypercube always do. The board is left alone, so this is a lot cheaper
+	/// than trying every move with `make_move`
+	pub fn legal_moves(&self) -> Vec<Move> {
+		self.scoring_swaps(usize::MAX)
 	}
 
-	/// Swap the places of two colors on the grid, this could be optimized to remove cloning
+	/// The first `limit` swaps that would score, for when all that matters is whether there are any
+	fn scoring_swaps(&self, limit: usize) -> Vec<Move> {
+		match self.board {
+			Some(ref board) => board.scoring_swaps(limit),
+			None => {
+				let mut moves = self.scoring_swaps_on_grid();
+				moves.truncate(limit);
+				moves
+			}
+		}
+	}
+
+	/// All the scoring swaps on boards too big for a bitboard, every swap is tried on a copy of the grid
+	fn scoring_swaps_on_grid(&self) -> Vec<Move> {
+		let (rows, cols) = (self.config.rows, self.config.cols);
+		let mut grid = self.grid.clone();
+		let mut moves = Vec::new();
+		for r in 0..rows {
+			for c in 0..cols {
+				let mut candidates = Vec::new();
+				if c + 1 < cols {
+					candidates.push(Move { row1: r, col1: c, row2: r, col2: c + 1 });
+				}
+				if r + 1 < rows {
+					candidates.push(Move { row1: r, col1: c, row2: r + 1, col2: c });
+				}
+				for mov in candidates {
+					let cube = |(r, c): Cell| self.grid[r][c].special == Special::Hypercube;
+					if cube((mov.row1, mov.col1)) || cube((mov.row2, mov.col2)) {
+						moves.push(mov);
+						continue;
+					}
+					swap(&mut grid, &mov);
+					if in_line(&grid, (mov.row1, mov.col1)) || in_line(&grid, (mov.row2, mov.col2)) {
+						moves.push(mov.clone());
+					}
+					swap(&mut grid, &mov);
+				}
+			}
+		}
+		moves
+	}
+
+	/// A move that would score, `None` if there aren't any left
+	pub fn hint(&self) -> Option<Move> {
+		self.scoring_swaps(1).into_iter().next()
+	}
+
+	/// Swap the places of two gems on the grid
 	pub fn execute_move(&mut self, mov: &Move) {
-		let moved = self.grid[mov.row1][mov.col1].clone();
-		self.grid[mov.row1][mov.col1] = self.grid[mov.row2][mov.col2].clone();
-		self.grid[mov.row2][mov.col2] = moved;
+		swap(&mut self.grid, mov);
+		if let Some(ref mut board) = self.board {
+			board.swap((mov.row1, mov.col1), (mov.row2, mov.col2));
+		}
+	}
+
+	/// Every line of 3 or more matching gems, first the ones in rows top down and then the ones in columns left to right
+	fn lines(&self) -> Vec<Vec<Cell>> {
+		match self.board {
+			Some(ref board) => board.lines(),
+			None => lines(&self.grid),
+		}
 	}
 
-	/// Gets all the pieces to remove from the board both row-wise and column-wise
+	/// The groups of matching gems on the board, each with its shape. A board in play never has any, they only
+	/// show up after changing the grid by hand, like with `execute_move`
+	pub fn match_groups(&self) -> Vec<MatchGroup> {
+		match_groups(&self.grid, self.lines())
+	}
+
+	/// Gets all the pieces to remove from the board both row-wise and column-wise, top down
 	pub fn pieces_to_remove(&self) -> Vec<Cell> {
-		let mut all_cells = HashSet::new();
-		for r in 0..8 {
-			for c in 0..8 {
-				let mut rows = self.contiguous((r, c), &row_neighbours);
-				let mut cols = self.contiguous((r, c), &col_neighbours);
-				if rows.len() >= 3 {
-					all_cells = all_cells.union(&mut rows).cloned().collect();
+		match self.board {
+			Some(ref board) => {
+				let matches = board.matches();
+				(0..self.config.rows).flat_map(|r| (0..self.config.cols).map(move |c| (r, c)))
+					.filter(|&cell| matches & board.bit(cell) != 0)
+					.collect()
+			}
+			None => {
+				let cells = self.match_groups().into_iter().flat_map(|group| group.cells).collect::<BTreeSet<_>>();
+				cells.into_iter().collect()
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A board without any blue or green gems and without matches, with blue gems on `cells`
+	fn board(cells: &[Cell]) -> Game {
+		let colors = [Color::Orange, Color::Purple, Color::Red, Color::White];
+		let mut game = Game::with_seed(1);
+		game.set_grid((0..8).map(|r| (0..8).map(|c| Gem::new(colors[(r % 2) * 2 + c % 2])).collect()).collect());
+		for &(r, c) in cells {
+			game.set_gem((r, c), Gem::new(Color::Blue));
+		}
+		game
+	}
+
+	fn row(r: usize, cols: ::std::ops::Range<usize>) -> Vec<Cell> {
+		cols.map(|c| (r, c)).collect()
+	}
+
+	fn col(rows: ::std::ops::Range<usize>, c: usize) -> Vec<Cell> {
+		rows.map(|r| (r, c)).collect()
+	}
+
+	fn shapes(cells: &[Cell]) -> Vec<Shape> {
+		board(cells).match_groups().iter().map(|group| group.shape).collect()
+	}
+
+	/// A board with no matches and no scoring swaps, the colors repeat in a pattern where no swap
+	/// can ever line up three of a kind
+	fn dead_grid() -> Grid {
+		(0..8).map(|r| (0..8).map(|c| Gem::new(Color::ALL[(r + 2 * c) % 3 + 3 * ((r / 2 + c / 2) % 2)])).collect())
+			.collect()
+	}
+
+	/// No three of a kind anywhere and at least one swap that scores
+	fn playable(game: &Game) -> bool {
+		game.pieces_to_remove().is_empty() && !game.legal_moves().is_empty()
+	}
+
+	fn count(grid: &Grid, color: Color) -> usize {
+		grid.iter().flat_map(|row| row.iter()).filter(|gem| gem.color == color).count()
+	}
+
+	fn blue(special: Special) -> Gem {
+		Gem { color: Color::Blue, special }
+	}
+
+	/// Puts blue gems on the board and swaps the gem at (6, 2) with the one below it
+	fn play(cells: &[Cell]) -> Game {
+		let mut game = board(cells);
+		assert!(game.make_move(&Move { row1: 6, col1: 2, row2: 7, col2: 2 }).made);
+		game
+	}
+
+	/// The board after a step of a cascade, from the board before it
+	fn replay(grid: &Grid, step: &CascadeStep) -> Grid {
+		let mut next = grid.clone();
+		for &((r, c), gem) in &step.created {
+			next[r][c] = gem;
+		}
+		let before = next.clone();
+		for &((r, c), (to_r, to_c)) in &step.falls {
+			next[to_r][to_c] = before[r][c];
+		}
+		for &((r, c), color) in &step.spawned {
+			next[r][c] = Gem::new(color);
+		}
+		next
+	}
+
+	fn specials(grid: &Grid) -> usize {
+		grid.iter().flat_map(|row| row.iter()).filter(|gem| gem.special != Special::None).count()
+	}
+
+	#[test]
+	fn line3() {
+		assert_eq!(shapes(&row(7, 0..3)), vec![Shape::Line3]);
+		assert_eq!(shapes(&col(0..3, 6)), vec![Shape::Line3]);
+	}
+
+	#[test]
+	fn line4() {
+		assert_eq!(shapes(&col(2..6, 4)), vec![Shape::Line4]);
+		assert_eq!(shapes(&row(1, 4..8)), vec![Shape::Line4]);
+	}
+
+	#[test]
+	fn line5() {
+		assert_eq!(shapes(&row(0, 1..6)), vec![Shape::Line5]);
+		assert_eq!(shapes(&row(3, 0..7)), vec![Shape::Line5], "longer lines count as 5");
+	}
+
+	#[test]
+	fn l() {
+		assert_eq!(shapes(&[row(7, 0..3), col(5..7, 0)].concat()), vec![Shape::L]);
+		assert_eq!(shapes(&[row(0, 5..8), col(1..3, 7)].concat()), vec![Shape::L]);
+	}
+
+	#[test]
+	fn t() {
+		assert_eq!(shapes(&[row(7, 0..3), col(5..7, 1)].concat()), vec![Shape::T]);
+		assert_eq!(shapes(&[row(4, 2..5), col(3..6, 2)].concat()), vec![Shape::T], "rows can end on columns too");
+	}
+
+	#[test]
+	fn cross() {
+		assert_eq!(shapes(&[row(5, 0..4), col(4..7, 1)].concat()), vec![Shape::Cross]);
+	}
+
+	#[test]
+	fn separate_matches_are_separate_groups() {
+		// Even when they touch
+		let groups = board(&[row(7, 0..3), row(7, 4..7), col(0..3, 5)].concat()).match_groups();
+		assert_eq!(groups.iter().map(|group| group.shape).collect::<Vec<_>>(), vec![Shape::Line3; 3]);
+		assert_eq!(groups.iter().map(|group| group.cells.len()).sum::<usize>(), 9);
+		let mut game = board(&row(6, 0..3));
+		for cell in row(5, 0..3) {
+			game.set_gem(cell, Gem::new(Color::Green));
+		}
+		assert_eq!(game.match_groups().len(), 2, "rows of different colors were put in one group");
+	}
+
+	#[test]
+	fn groups_score_on_their_own() {
+		// Two separate matches of 3 score 10 each, not as one match of 6
+		let mut game = board(&[(6, 0), (6, 1), (7, 2)]);
+		for &(r, c) in &[(7, 0), (7, 1), (6, 2)] {
+			game.set_gem((r, c), Gem::new(Color::Green));
+		}
+		let outcome = game.make_move(&Move { row1: 6, col1: 2, row2: 7, col2: 2 });
+		assert!(outcome.made);
+		let scores = outcome.steps[0].groups.iter().map(|&(ref group, score)| (group.shape, score)).collect::<Vec<_>>();
+		assert_eq!(scores, vec![(Shape::Line3, 10), (Shape::Line3, 10)]);
+		assert_eq!(outcome.steps[0].score, 20);
+	}
+
+	#[test]
+	fn cascades_multiply_the_score() {
+		// Every step of a cascade multiplies the score and adds the bonus
+		let group = &board(&row(7, 0..3)).match_groups()[0];
+		assert_eq!((group.score(0), group.score(1), group.score(2)), (10, 25, 40));
+		let group = &board(&[row(5, 0..4), col(4..7, 1)].concat()).match_groups()[0];
+		assert_eq!((group.score(0), group.score(1)), (60, 125));
+	}
+
+	#[test]
+	fn dead_grid_is_dead() {
+		let mut dead = Game::with_seed(0);
+		dead.set_grid(dead_grid());
+		assert!(dead.pieces_to_remove().is_empty(), "the dead grid has a match");
+		assert!(dead.legal_moves().is_empty(), "the dead grid has a scoring swap");
+	}
+
+	#[test]
+	fn dead_board_is_reshuffled() {
+		let grid = dead_grid();
+		let game = Game::from_grid(grid.clone(), 7);
+		assert_eq!(game.events, vec![Event::Reshuffled]);
+		assert!(playable(&game));
+		for &color in &Color::ALL {
+			assert_eq!(count(&game.grid, color), count(&grid, color), "reshuffling changed the pieces");
+		}
+		assert!(Game::from_grid(grid, 7) == game, "reshuffling isn't deterministic");
+	}
+
+	#[test]
+	fn dead_board_after_a_move_is_reshuffled() {
+		// Dead boards after a cascade are rare, playing hints on this seed runs into one after 26 moves
+		let mut game = Game::with_seed(31);
+		for _ in 0..26 {
+			assert!(game.events.is_empty());
+			let mov = game.hint().unwrap();
+			assert!(game.make_move(&mov).made);
+			assert!(playable(&game), "stuck after {} moves", game.moves);
+		}
+		assert_eq!(game.events, vec![Event::Reshuffled]);
+	}
+
+	#[test]
+	fn match_of_4_makes_a_line_gem() {
+		// Specials are made where the moved gem ends up, the bottom row doesn't move when pieces fall
+		let game = play(&[(7, 0), (7, 1), (6, 2), (7, 3)]);
+		assert_eq!(game.grid[7][2], blue(Special::Line));
+	}
+
+	#[test]
+	fn match_of_5_makes_a_hypercube() {
+		let game = play(&[(7, 0), (7, 1), (6, 2), (7, 3), (7, 4)]);
+		assert_eq!(game.grid[7][2].special, Special::Hypercube);
+	}
+
+	#[test]
+	fn l_shape_makes_a_bomb() {
+		let mut game = board(&[(7, 0), (7, 1), (7, 3), (5, 2), (6, 2)]);
+		assert!(game.make_move(&Move { row1: 7, col1: 2, row2: 7, col2: 3 }).made);
+		assert_eq!(game.grid[7][2], blue(Special::Bomb));
+	}
+
+	#[test]
+	fn matched_line_gem_clears_its_row_and_column() {
+		let mut game = board(&[(7, 1), (6, 2)]);
+		game.set_gem((7, 0), blue(Special::Line));
+		let outcome = game.make_move(&Move { row1: 6, col1: 2, row2: 7, col2: 2 });
+		// The 3 of the match and 12 more in the row and column
+		assert_eq!(outcome.steps[0].detonated, vec![((7, 0), Special::Line)]);
+		assert_eq!(outcome.steps[0].cleared.len(), 15);
+		assert_eq!(outcome.steps[0].score, 10 + 12 * DETONATION_SCORE);
+	}
+
+	#[test]
+	fn matched_bomb_clears_the_square_around_it() {
+		let mut game = board(&[(7, 0), (6, 2)]);
+		game.set_gem((7, 1), blue(Special::Bomb));
+		let outcome = game.make_move(&Move { row1: 6, col1: 2, row2: 7, col2: 2 });
+		assert_eq!(outcome.steps[0].detonated, vec![((7, 1), Special::Bomb)]);
+		assert_eq!(outcome.steps[0].cleared, vec![(6, 0), (6, 1), (6, 2), (7, 0), (7, 1), (7, 2)]);
+		assert_eq!(outcome.steps[0].score, 10 + 3 * DETONATION_SCORE);
+	}
+
+	#[test]
+	fn swapped_hypercube_clears_a_color() {
+		let mut game = board(&[]);
+		game.set_gem((4, 4), blue(Special::Hypercube));
+		let mov = Move { row1: 4, col1: 4, row2: 4, col2: 5 };
+		assert!(game.legal_moves().contains(&mov), "swapping a hypercube isn't a legal move");
+		let color = game.grid[4][5].color;
+		let cleared = count(&game.grid, color) + 1;
+		let outcome = game.make_move(&mov);
+		assert_eq!(outcome.steps[0].detonated[0], ((4, 4), Special::Hypercube));
+		assert_eq!(outcome.steps[0].cleared.len(), cleared);
+		assert_eq!(outcome.steps[0].score, cleared as i32 * DETONATION_SCORE);
+	}
+
+	#[test]
+	fn new_boards_have_no_specials() {
+		let small = GameConfig { rows: 5, cols: 5, colors: 3 };
+		for seed in 0..300 {
+			for game in &[Game::with_seed(seed), Game::with_config(small, seed)] {
+				assert_eq!(specials(&game.grid), 0, "seed {} starts with special gems", seed);
+				assert!(playable(game));
+				assert_eq!((game.score, game.moves), (0, 0));
+			}
+		}
+	}
+
+	#[test]
+	fn matches_on_a_given_grid_dont_make_specials() {
+		let mut grid = board(&row(3, 0..5)).grid;
+		for cell in [row(6, 2..6), col(0..3, 7)].concat() {
+			grid[cell.0][cell.1] = Gem::new(Color::Green);
+		}
+		let game = Game::from_grid(grid, 3);
+		assert_eq!(specials(&game.grid), 0);
+		assert!(playable(&game));
+	}
+
+	#[test]
+	fn outcome_replays_to_the_same_board() {
+		let (mut detonations, mut deepest) = (0, 0);
+		for seed in 0..20 {
+			let mut game = Game::with_seed(seed);
+			for _ in 0..60 {
+				let mov = game.hint().unwrap();
+				let (mut grid, score) = (game.grid.clone(), game.score);
+				let outcome = game.make_move(&mov);
+				assert!(outcome.made && outcome.depth() > 0);
+				assert_eq!(game.score - score, outcome.score(), "the steps don't add up to the score");
+				if game.events.contains(&Event::Reshuffled) {
+					continue;
 				}
-				if cols.len() >= 3 {
-					all_cells = all_cells.union(&mut cols).cloned().collect();
+				// Swapped hypercubes go off where they are, everything else is swapped first
+				let cube = |(r, c): Cell| grid[r][c].special == Special::Hypercube;
+				if !cube((mov.row1, mov.col1)) && !cube((mov.row2, mov.col2)) {
+					swap(&mut grid, &mov);
 				}
+				for (depth, step) in outcome.steps.iter().enumerate() {
+					assert_eq!(step.depth as usize, depth);
+					assert_eq!(step.spawned.len(), step.cleared.len());
+					grid = replay(&grid, step);
+					detonations += step.detonated.len();
+				}
+				assert!(grid == game.grid, "seed {} move {} doesn't replay", seed, game.moves);
+				deepest = deepest.max(outcome.depth());
 			}
 		}
-		let mut result = all_cells.drain().collect::<Vec<Cell>>();
-		// This sort is important because we need to remove cells from the top down in order
-		// to not mutate the pieces that we're working on while we're working on them
-		result.sort_by(|a, b| a.0.cmp(&b.0));
-		result
-	}
-
-	/// Start off the iteration of looking for contiguous cells using `contiguous_loop`
-	fn contiguous<F>(&self, cell: Cell, f: &F) -> HashSet<Cell>
-		where F: Fn(&Cell) -> Vec<Cell>
-	{
-		let mut visited: HashMap<Cell, bool> = HashMap::new();
-		let mut cells = HashSet::new();
-		cells.insert(cell);
-		self.contiguous_loop(&mut cells, &mut visited, cell, f);
-		cells
-	}
-
-	/// Start to accumulate in `contiguous_cells` the neighbours it finds of the same color and iterates to explore deeper
-	fn contiguous_loop<F>(&self,
-						  contiguous_cells: &mut HashSet<Cell>,
-						  visited: &mut HashMap<Cell, bool>,
-						  cell: Cell,
-						  f: &F)
-		where F: Fn(&Cell) -> Vec<Cell>
-	{
-		if visited.contains_key(&cell) {
-			return ();
-		} else {
-			visited.insert(cell, true);
-		}
-		for neighbour in f(&cell) {
-			if self.grid[cell.0][cell.1] == self.grid[neighbour.0][neighbour.1] &&
-			   !visited.contains_key(&neighbour) {
-				contiguous_cells.insert(neighbour);
-				self.contiguous_loop(contiguous_cells, visited, neighbour, f);
+		// Otherwise the specials and longer cascades went untested
+		assert!(detonations > 0 && deepest >= 3, "{} detonations, longest cascade {}", detonations, deepest);
+	}
+
+	#[test]
+	fn invalid_move_has_no_outcome() {
+		let mut game = Game::with_seed(0);
+		let before = game.clone();
+		let outcome = game.make_move(&Move { row1: 0, col1: 0, row2: 5, col2: 5 });
+		assert!(!outcome.made && outcome.steps.is_empty() && outcome.score() == 0);
+		assert!(game.grid == before.grid && game.moves == 0);
+	}
+
+	#[test]
+	fn simulate_matches_make_move() {
+		for seed in 0..5 {
+			let mut game = Game::with_seed(seed);
+			for _ in 0..10 {
+				for mov in game.legal_moves() {
+					let (next, outcome) = game.simulate(&mov).expect("a legal move didn't score");
+					let mut real = game.clone();
+					assert!(real.make_move(&mov) == outcome && real == next, "seed {} move {:?} doesn't match", seed, mov);
+					assert_eq!(next.moves, game.moves + 1);
+				}
+				let mov = game.hint().unwrap();
+				game.make_move(&mov);
 			}
 		}
 	}
+
+	#[test]
+	fn simulate_leaves_the_game_alone() {
+		let game = Game::with_seed(3);
+		let before = game.clone();
+		let mut rng = game_rng::seeded_rng(0);
+		for mov in game.legal_moves() {
+			game.simulate(&mov).unwrap();
+			game.simulate_sampled(&mov, &mut rng).unwrap();
+			assert!(game == before && game.clock == before.clock, "simulating {:?} changed the game", mov);
+		}
+		let bad = Move { row1: 0, col1: 0, row2: 5, col2: 5 };
+		assert!(game.simulate(&bad).is_none() && game.simulate_sampled(&bad, &mut rng).is_none());
+	}
+
+	#[test]
+	fn simulate_sampled_is_deterministic() {
+		let game = Game::with_seed(3);
+		let mov = game.hint().unwrap();
+		let sample = |seed| game.simulate_sampled(&mov, &mut game_rng::seeded_rng(seed)).unwrap();
+		assert!(sample(1) == sample(1), "the same samples came out differently");
+		assert!((2..10).any(|seed| sample(seed).0.grid != sample(1).0.grid), "sampling always brings in the same pieces");
+	}
 }
@@ -1,14 +1,27 @@
 extern crate bejeweled;
 
+use std::env;
 use std::io;
 use bejeweled::*;
 
+/// Pass a seed as the only argument to play the same game again, without a time-limit
 pub fn main() {
-	let mut game = Game::new(true);
+	let mut game = match env::args().nth(1).map(|seed| seed.parse()) {
+		Some(Ok(seed)) => Game::with_seed(seed),
+		Some(Err(_)) => {
+			println!("Usage: bejeweled-cli [SEED]");
+			return;
+		}
+		None => Game::new(true),
+	};
 	game.print_board();
 	loop {
 		let mut input = String::new();
 		match io::stdin().read_line(&mut input) {
+			Ok(_) if input.trim() == "?" => match game.hint() {
+				Some(mov) => println!("Try {} {} {} {}", mov.row1, mov.col1, mov.row2, mov.col2),
+				None => println!("There are no moves left"),
+			},
 			Ok(_) => {
 				let mut iter = input.split_whitespace();
 				let mov = Move {
@@ -17,8 +30,25 @@ pub fn main() {
 					row2: iter.next().map(str::parse).unwrap_or(Ok(0)).unwrap_or(0),
 					col2: iter.next().map(str::parse).unwrap_or(Ok(0)).unwrap_or(0),
 				};
-				game.make_move(&mov);
+				let outcome = game.make_move(&mov);
 				game.print_board();
+				if !outcome.made {
+					println!("That move doesn't score");
+				}
+				for step in &outcome.steps {
+					let shapes = step.groups.iter().map(|(group, _)| format!("{:?}", group.shape)).collect::<Vec<_>>();
+					let blown_up = if step.detonated.is_empty() { String::new() } else {
+						format!(", {} special gems went off", step.detonated.len())
+					};
+					println!("{}{}{}: {} points", "  ".repeat(step.depth as usize), shapes.join(" "), blown_up, step.score);
+				}
+				if game.events.contains(&Event::Reshuffled) {
+					println!("There were no moves left, so the board was reshuffled");
+				}
+				if game.is_over() {
+					println!("Time's up! You scored {} points in {} moves", game.score, game.moves);
+					break;
+				}
 			}
 			Err(error) => println!("Couldn't read input: {}", error),
 		}
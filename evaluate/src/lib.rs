@@ -0,0 +1,255 @@
+//! Plays bots against the games in this repo for many seeded episodes, so "my bot is better" can be
+//! backed up with numbers instead of watching a single game.
+//!
+//! Every episode is played with its own seed, for both the game and the bot. Two bots evaluated on the same
+//! seeds play exactly the same games, which is what makes the paired comparison in `stats` work.
+
+extern crate bejeweled;
+extern crate rand;
+extern crate rl;
+extern crate roguelike;
+extern crate taxi;
+extern crate taxi_learning_bot as bot;
+
+pub mod stats;
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use rand::{Rng, XorShiftRng};
+
+use rl::{seeded_rng, Agent, QLearning};
+
+/// The games bots can be evaluated on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GameKind {
+	/// The classic taxi with one passenger, as the taxi bot trains on it
+	Taxi,
+	Roguelike,
+	/// The classic 8x8 board with 6 colors, played for as many moves as the episode allows. Nobody wins it,
+	/// so only the score tells bots apart
+	Bejeweled,
+}
+
+impl FromStr for GameKind {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<GameKind, String> {
+		match s {
+			"taxi" => Ok(GameKind::Taxi),
+			"roguelike" => Ok(GameKind::Roguelike),
+			"bejeweled" => Ok(GameKind::Bejeweled),
+			_ => Err(format!("unknown game '{}', use taxi, roguelike or bejeweled", s)),
+		}
+	}
+}
+
+/// The bots that can be evaluated
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BotKind {
+	/// Moves in a random direction every time, or makes a random scoring swap in bejeweled
+	Random,
+	/// Takes a shortest path to wherever it needs to go next, a good bot should get close to this one.
+	/// In bejeweled it makes the swap that scores the most right away
+	Greedy,
+	/// A Q-table saved by the taxi bot, only for taxi
+	QTable(String),
+}
+
+impl FromStr for BotKind {
+	type Err = String;
+
+	/// `random`, `greedy` or `qtable:FILE`
+	fn from_str(s: &str) -> Result<BotKind, String> {
+		match s {
+			"random" => Ok(BotKind::Random),
+			"greedy" => Ok(BotKind::Greedy),
+			_ if s.starts_with("qtable:") => Ok(BotKind::QTable(s["qtable:".len()..].to_string())),
+			_ => Err(format!("unknown bot '{}', use random, greedy or qtable:FILE", s)),
+		}
+	}
+}
+
+/// How one episode went
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Episode {
+	pub seed: u32,
+	/// Moves made, at most the maximum the episode was played with
+	pub moves: u32,
+	/// The game's own score
+	pub score: f64,
+	pub won: bool,
+}
+
+/// Plays an episode for every seed. Games that aren't over after `max_moves` moves are stopped and count as lost.
+pub fn evaluate(game: GameKind, bot: &BotKind, seeds: &[u32], max_moves: u32) -> Result<Vec<Episode>, String> {
+	let table = match *bot {
+		BotKind::QTable(ref path) => {
+			if game != GameKind::Taxi {
+				return Err("Q-table bots can only play taxi".to_string());
+			}
+			let mut agent = QLearning::new(0.0, 0.0);
+			agent.table = bot::table::load(path, bot::STATES)
+				.map_err(|e| format!("Couldn't load Q-table from {}: {}", path, e))?;
+			Some(agent)
+		}
+		_ => None,
+	};
+	Ok(seeds.iter().map(|&seed| {
+		let mut rng = seeded_rng(seed);
+		match game {
+			GameKind::Taxi => play_taxi(bot, table.as_ref(), seed, max_moves, &mut rng),
+			GameKind::Roguelike => play_roguelike(bot, seed, max_moves, &mut rng),
+			GameKind::Bejeweled => play_bejeweled(bot, seed, max_moves, &mut rng),
+		}
+	}).collect())
+}
+
+fn play_taxi(kind: &BotKind, table: Option<&QLearning<u32, taxi::Dir>>, seed: u32, max_moves: u32,
+			 rng: &mut XorShiftRng) -> Episode {
+	let config = taxi::GameConfig { seed: Some(seed), ..taxi::GameConfig::default() };
+	let mut game = taxi::Game::with_config(config, false);
+	let mut moves = 0;
+	while !game.is_over() && moves < max_moves {
+		let dir = match (kind, table) {
+			(_, Some(agent)) => agent.best_action(&bot::q_state(&game), &bot::ACTIONS),
+			(&BotKind::Greedy, _) => {
+				let target = if game.passenger_picked_up() { game.goal_position() } else { game.passenger_position() };
+				let position = game.player_position();
+				// Measured from the target, walls can't be reached from there and come out as `None`
+				shortest(&bot::ACTIONS, rng, |&dir| game.travel_distance(target, taxi_neighbour(position, dir)))
+			}
+			_ => *rng.choose(&bot::ACTIONS).unwrap(),
+		};
+		game.make_move(dir);
+		moves += 1;
+	}
+	Episode { seed, moves, score: game.score() as f64, won: game.has_won() }
+}
+
+fn taxi_neighbour((r, c): (u32, u32), dir: taxi::Dir) -> (u32, u32) {
+	match dir {
+		taxi::Dir::Up => (r - 1, c),
+		taxi::Dir::Right => (r, c + 1),
+		taxi::Dir::Down => (r + 1, c),
+		taxi::Dir::Left => (r, c - 1),
+	}
+}
+
+/// The roguelike has no randomness of its own, so the seed only changes what a random bot does
+fn play_roguelike(kind: &BotKind, seed: u32, max_moves: u32, rng: &mut XorShiftRng) -> Episode {
+	let mut game = roguelike::Game::new(false);
+	let distances = goal_distances(&game);
+	let mut moves = 0;
+	while !game.has_won() && moves < max_moves {
+		let dir = match *kind {
+			BotKind::Greedy => {
+				let (r, c) = game.position;
+				shortest(&[0, 1, 2, 3], rng, |&dir| {
+					let (r, c) = roguelike_neighbour((r, c), dir);
+					distances[r * roguelike::COL_SIZE + c]
+				})
+			}
+			_ => rng.gen_range(0, 4),
+		};
+		game.enter_move(&roguelike::Dir::from_u32(&dir), false);
+		moves += 1;
+	}
+	Episode { seed, moves, score: game.get_score() as f64, won: game.has_won() }
+}
+
+/// The greedy bot doesn't get to see the new pieces before they come in, it guesses them with its own RNG
+fn play_bejeweled(kind: &BotKind, seed: u32, max_moves: u32, rng: &mut XorShiftRng) -> Episode {
+	let mut game = bejeweled::Game::with_seed(seed);
+	game.limit = bejeweled::Limit::Moves(max_moves as i32);
+	while !game.is_over() {
+		let legal = game.legal_moves();
+		let mov = match *kind {
+			BotKind::Greedy => {
+				let scores = legal.iter().map(|mov| game.simulate_sampled(mov, rng).map_or(0, |(_, outcome)| outcome.score()))
+					.collect::<Vec<_>>();
+				let best = scores.iter().cloned().max().unwrap();
+				let choices = legal.iter().zip(&scores).filter(|&(_, &s)| s == best).map(|(mov, _)| mov).collect::<Vec<_>>();
+				(*rng.choose(&choices).unwrap()).clone()
+			}
+			_ => rng.choose(&legal).unwrap().clone(),
+		};
+		game.make_move(&mov);
+	}
+	Episode { seed, moves: game.moves as u32, score: game.score as f64, won: false }
+}
+
+fn roguelike_neighbour((r, c): (usize, usize), dir: u32) -> (usize, usize) {
+	match dir {
+		0 => (r - 1, c),
+		1 => (r, c + 1),
+		2 => (r + 1, c),
+		_ => (r, c - 1),
+	}
+}
+
+/// Moves to the goal from every cell of the roguelike map, walking through enemies. Walls never change,
+/// so this only has to be worked out once per game.
+fn goal_distances(game: &roguelike::Game) -> Vec<Option<u32>> {
+	let cells = roguelike::ROW_SIZE * roguelike::COL_SIZE;
+	let grid = game.grid();
+	let (walls, goals) = (&grid[..cells], &grid[4 * cells..5 * cells]);
+	let mut distances = vec![None; cells];
+	let mut queue = VecDeque::new();
+	for cell in (0..cells).filter(|&i| goals[i] > 0.0) {
+		distances[cell] = Some(0);
+		queue.push_back(cell);
+	}
+	while let Some(cell) = queue.pop_front() {
+		let d = distances[cell].unwrap();
+		let (r, c) = (cell / roguelike::COL_SIZE, cell % roguelike::COL_SIZE);
+		for dir in 0..4 {
+			let (r, c) = roguelike_neighbour((r, c), dir);
+			let next = r * roguelike::COL_SIZE + c;
+			if walls[next] == 0.0 && distances[next].is_none() {
+				distances[next] = Some(d + 1);
+				queue.push_back(next);
+			}
+		}
+	}
+	distances
+}
+
+/// The action with the shortest distance, ties are broken at random and unreachable ones are never picked
+/// unless nothing is reachable
+fn shortest<A: Copy, R: Rng, F>(actions: &[A], rng: &mut R, distance: F) -> A where F: Fn(&A) -> Option<u32> {
+	let distances = actions.iter().map(|a| distance(a).unwrap_or(u32::MAX)).collect::<Vec<_>>();
+	let best = *distances.iter().min().unwrap();
+	let choices = actions.iter().zip(&distances).filter(|&(_, &d)| d == best).map(|(&a, _)| a).collect::<Vec<_>>();
+	*rng.choose(&choices).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn total_score(game: GameKind, bot: BotKind) -> f64 {
+		evaluate(game, &bot, &[1, 2, 3, 4, 5], 30).unwrap().iter().map(|e| e.score).sum()
+	}
+
+	#[test]
+	fn same_seeds_play_the_same_games() {
+		for &game in &[GameKind::Taxi, GameKind::Roguelike, GameKind::Bejeweled] {
+			assert_eq!(evaluate(game, &BotKind::Random, &[7, 8], 30), evaluate(game, &BotKind::Random, &[7, 8], 30));
+		}
+	}
+
+	#[test]
+	fn greedy_beats_random() {
+		let seeds = (0..10).collect::<Vec<_>>();
+		assert!(evaluate(GameKind::Taxi, &BotKind::Greedy, &seeds, 200).unwrap().iter().all(|e| e.won));
+		assert!(total_score(GameKind::Bejeweled, BotKind::Greedy) > total_score(GameKind::Bejeweled, BotKind::Random));
+	}
+
+	#[test]
+	fn bejeweled_plays_every_move() {
+		let episodes = evaluate(GameKind::Bejeweled, &BotKind::Random, &[1, 2], 25).unwrap();
+		assert!(episodes.iter().all(|e| e.moves == 25 && e.score > 0.0 && !e.won));
+		assert!(evaluate(GameKind::Bejeweled, &BotKind::QTable("table.bin".to_string()), &[1], 25).is_err());
+	}
+}
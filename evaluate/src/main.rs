@@ -0,0 +1,165 @@
+extern crate evaluate;
+
+use std::env;
+use std::fs::File;
+use std::io::{self, Write};
+use std::process;
+
+use evaluate::stats::{wilson, PairedTest, Summary};
+use evaluate::{evaluate, BotKind, Episode, GameKind};
+
+struct Options {
+	game: GameKind,
+	bot: BotKind,
+	/// Second bot to compare the first one with, on the same games
+	against: Option<BotKind>,
+	episodes: u32,
+	/// Seed of the first episode, the next ones count up from here
+	seed: u32,
+	max_moves: u32,
+	/// Where to write every episode of every bot as CSV
+	output: Option<String>,
+}
+
+impl Options {
+	fn from_args() -> Options {
+		let mut options = Options {
+			game: GameKind::Taxi,
+			bot: BotKind::Greedy,
+			against: None,
+			episodes: 1000,
+			seed: 0,
+			max_moves: 200,
+			output: None,
+		};
+		let mut args = env::args().skip(1);
+		while let Some(flag) = args.next() {
+			if flag == "--help" || flag == "-h" {
+				usage();
+			}
+			let value = args.next().unwrap_or_else(|| usage());
+			match flag.as_str() {
+				"--game" => options.game = value.parse().unwrap_or_else(|e| fail(e)),
+				"--bot" => options.bot = value.parse().unwrap_or_else(|e| fail(e)),
+				"--against" => options.against = Some(value.parse().unwrap_or_else(|e| fail(e))),
+				"--episodes" => options.episodes = value.parse().unwrap_or_else(|_| usage()),
+				"--seed" => options.seed = value.parse().unwrap_or_else(|_| usage()),
+				"--max-moves" => options.max_moves = value.parse().unwrap_or_else(|_| usage()),
+				"--output" => options.output = Some(value),
+				_ => usage(),
+			}
+		}
+		if options.episodes < 2 {
+			fail("Play at least 2 episodes to get any statistics".to_string());
+		}
+		options
+	}
+}
+
+fn usage() -> ! {
+	println!("Usage: evaluate [--game taxi|roguelike|bejeweled] [--bot BOT] [--against BOT] [--episodes N] [--seed S] \
+			  [--max-moves N] [--output FILE]");
+	println!();
+	println!("  --game       the game to play (default taxi)");
+	println!("  --bot        random, greedy or qtable:FILE with a Q-table saved by the taxi bot (default greedy)");
+	println!("  --against    compare with this bot, on the same games");
+	println!("  --episodes   number of games to play (default 1000)");
+	println!("  --seed       seed of the first game, the others count up from it (default 0)");
+	println!("  --max-moves  games that take longer than this are stopped and lost (default 200)");
+	println!("  --output     write every game of every bot to a CSV file");
+	process::exit(1)
+}
+
+fn fail(message: String) -> ! {
+	println!("{}", message);
+	process::exit(1)
+}
+
+fn name(bot: &BotKind) -> String {
+	match *bot {
+		BotKind::Random => "random".to_string(),
+		BotKind::Greedy => "greedy".to_string(),
+		BotKind::QTable(ref path) => format!("qtable:{}", path),
+	}
+}
+
+fn moves(episodes: &[Episode]) -> Vec<f64> {
+	episodes.iter().map(|e| e.moves as f64).collect()
+}
+
+fn scores(episodes: &[Episode]) -> Vec<f64> {
+	episodes.iter().map(|e| e.score).collect()
+}
+
+fn wins(episodes: &[Episode]) -> Vec<f64> {
+	episodes.iter().map(|e| if e.won { 1.0 } else { 0.0 }).collect()
+}
+
+fn report(bot: &BotKind, episodes: &[Episode]) {
+	println!("{}", name(bot));
+	println!("  {:<6} {:>9} {:>9} {:>9}  95% CI of the mean", "", "mean", "median", "stdev");
+	for &(label, ref values) in &[("moves", moves(episodes)), ("score", scores(episodes))] {
+		let s = Summary::of(values).unwrap();
+		println!("  {:<6} {:>9.2} {:>9.2} {:>9.2}  {:.2} .. {:.2}", label, s.mean, s.median, s.stdev, s.ci.0, s.ci.1);
+	}
+	let won = episodes.iter().filter(|e| e.won).count();
+	let (low, high) = wilson(won, episodes.len());
+	println!("  won {:.1}% of the games, 95% CI {:.1}% .. {:.1}%", 100.0 * won as f64 / episodes.len() as f64,
+			 100.0 * low, 100.0 * high);
+	println!();
+}
+
+fn compare(a: &BotKind, first: &[Episode], b: &BotKind, second: &[Episode]) {
+	println!("{} minus {}, paired over the same {} games", name(a), name(b), first.len());
+	println!("  {:<6} {:>10}  {:<24} {:>8} {:>9}", "", "difference", "95% CI", "t", "p");
+	let rows = [
+		("moves", moves(first), moves(second)),
+		("score", scores(first), scores(second)),
+		("won", wins(first), wins(second)),
+	];
+	for &(label, ref x, ref y) in &rows {
+		let test = PairedTest::of(x, y).unwrap();
+		println!("  {:<6} {:>10.3}  {:<24} {:>8.2} {:>9.4}{}", label, test.mean_difference,
+				 format!("{:.3} .. {:.3}", test.ci.0, test.ci.1), test.t, test.p_value,
+				 if test.significant() { "  significant" } else { "" });
+	}
+	println!();
+	println!("Differences with p below 0.05 are unlikely to be luck. The win difference is in games won per game played.");
+}
+
+fn write_csv(path: &str, results: &[(&BotKind, &[Episode])]) -> io::Result<()> {
+	let mut out = File::create(path)?;
+	writeln!(out, "bot,seed,moves,score,won")?;
+	for &(bot, episodes) in results {
+		for e in episodes {
+			writeln!(out, "{},{},{},{},{}", name(bot), e.seed, e.moves, e.score, if e.won { 1 } else { 0 })?;
+		}
+	}
+	Ok(())
+}
+
+fn main() {
+	let options = Options::from_args();
+	let seeds = (0..options.episodes).map(|i| options.seed.wrapping_add(i)).collect::<Vec<_>>();
+	println!("Playing {} games of {:?}, seeds {} to {}, at most {} moves each", options.episodes, options.game,
+			 seeds[0], seeds[seeds.len() - 1], options.max_moves);
+	println!();
+
+	let first = evaluate(options.game, &options.bot, &seeds, options.max_moves).unwrap_or_else(|e| fail(e));
+	report(&options.bot, &first);
+	let mut results = vec![(&options.bot, &first[..])];
+
+	let second;
+	if let Some(ref against) = options.against {
+		second = evaluate(options.game, against, &seeds, options.max_moves).unwrap_or_else(|e| fail(e));
+		report(against, &second);
+		compare(&options.bot, &first, against, &second);
+		results.push((against, &second[..]));
+	}
+
+	if let Some(ref path) = options.output {
+		if let Err(e) = write_csv(path, &results) {
+			println!("Couldn't write results to {}: {}", path, e);
+		}
+	}
+}
@@ -0,0 +1,265 @@
+//! The statistics for comparing bots: averages with confidence intervals and a paired t-test.
+//!
+//! Everything works at the 95% level. The intervals for averages use Student's t-distribution, which is
+//! what you want for the few dozen to few thousand games an evaluation usually plays.
+
+/// Describes a sample of numbers, like the moves of every game a bot played
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+	pub n: usize,
+	pub mean: f64,
+	pub median: f64,
+	/// Sample standard deviation
+	pub stdev: f64,
+	/// 95% confidence interval for the mean
+	pub ci: (f64, f64),
+}
+
+impl Summary {
+	/// Describes the values, `None` if there are fewer than two of them
+	pub fn of(values: &[f64]) -> Option<Summary> {
+		let n = values.len();
+		if n < 2 {
+			return None;
+		}
+		let (mean, stdev) = mean_stdev(values);
+		let mut sorted = values.to_vec();
+		sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+		let median = if n % 2 == 0 { (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0 } else { sorted[n / 2] };
+		let margin = t_quantile(0.975, (n - 1) as f64) * stdev / (n as f64).sqrt();
+		Some(Summary { n, mean, median, stdev, ci: (mean - margin, mean + margin) })
+	}
+}
+
+/// Compares two bots that played the same games, game by game
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PairedTest {
+	pub n: usize,
+	/// Average of the first bot's values minus the second's
+	pub mean_difference: f64,
+	/// 95% confidence interval for the mean difference
+	pub ci: (f64, f64),
+	pub t: f64,
+	/// Probability of a difference at least this big if the bots were equally good, two-sided
+	pub p_value: f64,
+}
+
+impl PairedTest {
+	/// Tests whether `a` and `b` differ on average, `None` unless they have the same length of at least two
+	pub fn of(a: &[f64], b: &[f64]) -> Option<PairedTest> {
+		let n = a.len();
+		if n < 2 || b.len() != n {
+			return None;
+		}
+		let differences = a.iter().zip(b).map(|(a, b)| a - b).collect::<Vec<_>>();
+		let (mean, stdev) = mean_stdev(&differences);
+		let df = (n - 1) as f64;
+		let error = stdev / (n as f64).sqrt();
+		let margin = t_quantile(0.975, df) * error;
+		let (t, p_value) = if error > 0.0 {
+			let t = mean / error;
+			(t, 2.0 * (1.0 - t_cdf(t.abs(), df)))
+		} else if mean == 0.0 {
+			// Every game went exactly the same for both
+			(0.0, 1.0)
+		} else {
+			// The same difference in every game
+			(mean.signum() * f64::INFINITY, 0.0)
+		};
+		Some(PairedTest { n, mean_difference: mean, ci: (mean - margin, mean + margin), t, p_value })
+	}
+
+	/// Whether the difference is significant at the 5% level
+	pub fn significant(&self) -> bool {
+		self.p_value < 0.05
+	}
+}
+
+/// 95% Wilson score interval for the fraction of `successes` out of `n`, which unlike the usual
+/// normal approximation stays between 0 and 1 and works for fractions close to them
+pub fn wilson(successes: usize, n: usize) -> (f64, f64) {
+	if n == 0 {
+		return (0.0, 1.0);
+	}
+	let z = 1.959_964;
+	let (n, p) = (n as f64, successes as f64 / n as f64);
+	let centre = (p + z * z / (2.0 * n)) / (1.0 + z * z / n);
+	let margin = z / (1.0 + z * z / n) * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt();
+	((centre - margin).max(0.0), (centre + margin).min(1.0))
+}
+
+fn mean_stdev(values: &[f64]) -> (f64, f64) {
+	let n = values.len() as f64;
+	let mean = values.iter().sum::<f64>() / n;
+	let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+	(mean, variance.sqrt())
+}
+
+/// Probability that Student's t-distribution with `df` degrees of freedom is at most `t`
+pub fn t_cdf(t: f64, df: f64) -> f64 {
+	if t.is_infinite() {
+		return if t > 0.0 { 1.0 } else { 0.0 };
+	}
+	let tail = 0.5 * incomplete_beta(df / 2.0, 0.5, df / (df + t * t));
+	if t > 0.0 { 1.0 - tail } else { tail }
+}
+
+/// The `t` for which `t_cdf(t, df)` is `p`, found by bisection
+pub fn t_quantile(p: f64, df: f64) -> f64 {
+	if p < 0.5 {
+		return -t_quantile(1.0 - p, df);
+	}
+	let mut high = 1.0;
+	while t_cdf(high, df) < p {
+		high *= 2.0;
+	}
+	let mut low = 0.0;
+	for _ in 0..100 {
+		let middle = (low + high) / 2.0;
+		if t_cdf(middle, df) < p {
+			low = middle;
+		} else {
+			high = middle;
+		}
+	}
+	(low + high) / 2.0
+}
+
+/// The regularized incomplete beta function, with the continued fraction from Numerical Recipes
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+	if x <= 0.0 {
+		return 0.0;
+	}
+	if x >= 1.0 {
+		return 1.0;
+	}
+	let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+	// The continued fraction converges quickly on this side, the other side follows from symmetry
+	if x < (a + 1.0) / (a + b + 2.0) {
+		front * beta_fraction(a, b, x) / a
+	} else {
+		1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+	}
+}
+
+/// Lentz's method for the continued fraction of the incomplete beta function
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+	const TINY: f64 = 1e-300;
+	let mut c = 1.0;
+	let mut d = 1.0 - (a + b) * x / (a + 1.0);
+	if d.abs() < TINY {
+		d = TINY;
+	}
+	d = 1.0 / d;
+	let mut fraction = d;
+	for m in 1..300 {
+		let m = m as f64;
+		for &numerator in &[
+			m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+			-(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+		] {
+			d = 1.0 + numerator * d;
+			if d.abs() < TINY {
+				d = TINY;
+			}
+			c = 1.0 + numerator / c;
+			if c.abs() < TINY {
+				c = TINY;
+			}
+			d = 1.0 / d;
+			fraction *= d * c;
+		}
+		if (d * c - 1.0).abs() < 1e-15 {
+			break;
+		}
+	}
+	fraction
+}
+
+/// Logarithm of the gamma function, Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+	const COEFFICIENTS: [f64; 9] = [
+		0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8, 771.323_428_777_653_1,
+		-176.615_029_162_140_6, 12.507_343_278_686_905, -0.138_571_095_265_720_12,
+		9.984_369_578_019_572e-6, 1.505_632_735_149_311_6e-7,
+	];
+	if x < 0.5 {
+		// Reflection formula
+		return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+	}
+	let x = x - 1.0;
+	let t = x + 7.5;
+	let sum = COEFFICIENTS[1..].iter().enumerate().fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+	0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn close(a: f64, b: f64, tolerance: f64) -> bool {
+		(a - b).abs() < tolerance
+	}
+
+	#[test]
+	fn t_distribution_matches_tables() {
+		assert!(close(t_quantile(0.975, 9.0), 2.2622, 1e-4));
+		assert!(close(t_quantile(0.975, 1.0), 12.7062, 1e-4));
+		assert!(close(t_quantile(0.975, 30.0), 2.0423, 1e-4));
+		assert!(close(t_quantile(0.025, 9.0), -2.2622, 1e-4));
+		assert!(close(t_cdf(2.0, 10.0), 0.9633, 1e-4));
+		assert!(close(t_cdf(-2.0, 10.0), 1.0 - 0.9633, 1e-4));
+		assert_eq!(t_cdf(0.0, 5.0), 0.5);
+		assert_eq!(t_cdf(f64::INFINITY, 5.0), 1.0);
+	}
+
+	#[test]
+	fn incomplete_beta_matches_closed_forms() {
+		// I_x(2, 3) is a binomial sum, I_x(1/2, 1/2) the arcsine distribution
+		assert!(close(incomplete_beta(2.0, 3.0, 0.4), 0.5248, 1e-12));
+		assert!(close(incomplete_beta(0.5, 0.5, 0.3), 2.0 / std::f64::consts::PI * 0.3f64.sqrt().asin(), 1e-12));
+		assert!(close(incomplete_beta(3.0, 1.0, 0.7), 0.343, 1e-12));
+		assert!(close(incomplete_beta(4.0, 4.0, 0.5), 0.5, 1e-12));
+		assert_eq!(incomplete_beta(2.0, 2.0, 0.0), 0.0);
+		assert_eq!(incomplete_beta(2.0, 2.0, 1.0), 1.0);
+		assert!(close(ln_gamma(5.0), 24f64.ln(), 1e-12));
+		assert!(close(ln_gamma(0.5), std::f64::consts::PI.sqrt().ln(), 1e-12));
+	}
+
+	#[test]
+	fn paired_test_of_known_differences() {
+		// Differences of 1, 2 and 3: with 2 degrees of freedom the t-distribution has a closed form
+		let test = PairedTest::of(&[3.0, 5.0, 7.0], &[2.0, 3.0, 4.0]).unwrap();
+		assert_eq!(test.mean_difference, 2.0);
+		assert!(close(test.t, 12f64.sqrt(), 1e-12));
+		assert!(close(test.p_value, 1.0 - 12f64.sqrt() / 14f64.sqrt(), 1e-9));
+		assert!(close(test.ci.0, 2.0 - 2.484_138, 1e-5) && close(test.ci.1, 2.0 + 2.484_138, 1e-5));
+		assert!(!test.significant());
+
+		let same = PairedTest::of(&[1.0, 2.0], &[1.0, 2.0]).unwrap();
+		assert_eq!((same.t, same.p_value), (0.0, 1.0));
+		let shifted = PairedTest::of(&[2.0, 3.0], &[1.0, 2.0]).unwrap();
+		assert_eq!((shifted.t, shifted.p_value), (f64::INFINITY, 0.0));
+		assert!(PairedTest::of(&[1.0, 2.0], &[1.0]).is_none());
+	}
+
+	#[test]
+	fn wilson_interval_stays_in_range() {
+		let (low, high) = wilson(5, 10);
+		assert!(close(low, 0.2366, 1e-4) && close(high, 0.7634, 1e-4));
+		let (low, high) = wilson(0, 10);
+		assert!(low == 0.0 && close(high, 0.2775, 1e-4));
+		let (low, high) = wilson(95, 100);
+		assert!(close(low, 0.8882, 1e-4) && close(high, 0.9785, 1e-4));
+		assert_eq!(wilson(0, 0), (0.0, 1.0));
+	}
+
+	#[test]
+	fn summary_of_a_sample() {
+		let s = Summary::of(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+		assert_eq!((s.n, s.mean, s.median), (4, 2.5, 2.5));
+		assert!(close(s.stdev, (5.0f64 / 3.0).sqrt(), 1e-12));
+		assert_eq!(Summary::of(&[1.0, 5.0, 2.0]).unwrap().median, 2.0);
+		assert!(Summary::of(&[1.0]).is_none());
+	}
+}
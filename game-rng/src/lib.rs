@@ -0,0 +1,83 @@
+//! The seeded random number generator shared by the games and the learning code, so the same seed
+//! always plays out the same way everywhere.
+
+extern crate rand;
+
+use std::hash::{Hash, Hasher};
+
+use rand::{SeedableRng, XorShiftRng};
+
+/// A random number generator that always produces the same numbers for the same seed
+pub fn seeded_rng(seed: u32) -> XorShiftRng {
+	// XorShift can't be seeded with all zeroes, the constants make sure that never happens
+	XorShiftRng::from_seed([seed, 0x193a_6754, 0xa8a7_d469, 0x9783_0e05])
+}
+
+/// The random number generator owned by a game.
+/// It's not part of the observable state, so all game RNGs compare and hash as equal.
+#[derive(Clone)]
+pub struct GameRng(pub XorShiftRng);
+
+impl GameRng {
+	/// Seeded with `seed`, or randomly if there isn't one
+	pub fn new(seed: Option<u32>) -> GameRng {
+		match seed {
+			Some(seed) => GameRng(seeded_rng(seed)),
+			None => GameRng(rand::weak_rng()),
+		}
+	}
+}
+
+impl PartialEq for GameRng {
+	fn eq(&self, _: &GameRng) -> bool {
+		true
+	}
+}
+
+impl Eq for GameRng {}
+
+impl Hash for GameRng {
+	fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::Rng;
+
+	fn numbers(seed: u32) -> Vec<u32> {
+		let mut rng = seeded_rng(seed);
+		(0..20).map(|_| rng.next_u32()).collect()
+	}
+
+	#[test]
+	fn same_seed_same_numbers() {
+		assert_eq!(numbers(3), numbers(3));
+		assert_eq!(numbers(0), numbers(0));
+		let mut game = GameRng::new(Some(3));
+		assert_eq!((0..20).map(|_| game.0.next_u32()).collect::<Vec<_>>(), numbers(3));
+	}
+
+	#[test]
+	fn different_seeds_different_numbers() {
+		let sequences = (0..50).map(numbers).collect::<Vec<_>>();
+		for (i, a) in sequences.iter().enumerate() {
+			for b in &sequences[i + 1..] {
+				assert!(a != b);
+			}
+		}
+	}
+
+	#[test]
+	fn seed_0_works() {
+		// A seed of all zeroes would make XorShift return nothing but zeroes
+		let numbers = numbers(0);
+		assert!(numbers.iter().any(|&n| n != 0));
+		assert!(numbers.windows(2).any(|w| w[0] != w[1]));
+	}
+
+	#[test]
+	fn game_rngs_are_all_equal() {
+		assert!(GameRng::new(Some(1)) == GameRng::new(None));
+	}
+}
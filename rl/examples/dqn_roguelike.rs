@@ -0,0 +1,129 @@
+//! Trains a deep Q-network on the roguelike from the grid the game draws of itself, then plays a game
+//! without exploring. Run with `cargo run --release --example dqn_roguelike`.
+//! The goal is too far away to stumble upon by chance, so the rewards are shaped by how many moves away from
+//! the goal the player is, which the example works out from the walls in the grid.
+
+extern crate rl;
+extern crate roguelike;
+
+use std::collections::VecDeque;
+use std::process;
+
+use rl::dqn::{Dqn, DqnConfig, Observe};
+use rl::*;
+use roguelike::{Action, Dir, Game, COL_SIZE, GRID_PLANES, ROW_SIZE};
+
+/// The roguelike's `Dir` can't be copied, so the agent picks its index instead
+const ACTIONS: [u32; 4] = [0, 1, 2, 3];
+const DISCOUNT: f64 = 0.99;
+const EPISODES: u64 = 500;
+const MAX_MOVES: u64 = 200;
+
+/// The roguelike with rewards for the points the player scores, a small cost for every move and
+/// a big reward for reaching the goal
+struct Roguelike {
+	game: Game,
+	/// Number of moves from every cell to the goal, going through enemies but not walls
+	distances: Vec<Option<u32>>,
+}
+
+impl Roguelike {
+	fn new() -> Roguelike {
+		let game = Game::new(false);
+		let grid = game.grid();
+		let cells = ROW_SIZE * COL_SIZE;
+		let goal = (0..cells).find(|&i| grid[4 * cells + i] == 1.0).unwrap();
+		let mut distances = vec![None; cells];
+		distances[goal] = Some(0);
+		let mut queue = VecDeque::new();
+		queue.push_back(goal);
+		while let Some(cell) = queue.pop_front() {
+			let (r, c) = (cell / COL_SIZE, cell % COL_SIZE);
+			let next = [(r - 1, c), (r + 1, c), (r, c - 1), (r, c + 1)];
+			for &(r, c) in &next {
+				let i = r * COL_SIZE + c;
+				if grid[i] == 0.0 && distances[i].is_none() {
+					distances[i] = Some(distances[cell].unwrap() + 1);
+					queue.push_back(i);
+				}
+			}
+		}
+		Roguelike { game, distances }
+	}
+
+	/// How good a position is to be in for the shaping of the rewards, higher is closer to the goal
+	fn potential(&self) -> f64 {
+		let (r, c) = self.game.position;
+		-(self.distances[r * COL_SIZE + c].unwrap() as f64) / 10.0
+	}
+}
+
+impl Environment for Roguelike {
+	type State = Game;
+	type Action = u32;
+
+	fn actions(&self) -> Vec<u32> {
+		ACTIONS.to_vec()
+	}
+	fn reset(&mut self) -> Game {
+		self.game = Game::new(false);
+		self.game.clone()
+	}
+	fn step(&mut self, action: u32) -> Step<Game> {
+		let score = self.game.get_score();
+		let potential = self.potential();
+		let won = self.game.enter_move(&Dir::from_u32(&action), false);
+		let mut reward = (self.game.get_score() - score) as f64 / 10.0 - 0.1;
+		if self.game.action == Action::WalkedIntoWall {
+			reward -= 0.5;
+		}
+		reward += DISCOUNT * self.potential() - potential;
+		if won {
+			reward += 10.0;
+		}
+		Step {
+			state: self.game.clone(),
+			reward,
+			done: won,
+		}
+	}
+	fn won(&self) -> bool {
+		self.game.has_won()
+	}
+}
+
+/// Shows the network the whole map
+struct Grid;
+
+impl Observe<Game> for Grid {
+	fn size(&self) -> usize {
+		GRID_PLANES * ROW_SIZE * COL_SIZE
+	}
+	fn observe(&self, game: &Game) -> Vec<f32> {
+		game.grid()
+	}
+}
+
+fn main() {
+	let config = DqnConfig { discount: DISCOUNT, ..DqnConfig::default() };
+	let mut agent = Dqn::new(Grid, ACTIONS.to_vec(), config, 1);
+	let mut env = Roguelike::new();
+	let epsilon = Epsilon::Linear { start: 1.0, end: 0.05, steps: EPISODES * MAX_MOVES / 2 };
+	let mut trainer = Trainer::new(epsilon, Period::Episodes(EPISODES), 1);
+	trainer.max_episode_length = MAX_MOVES;
+	trainer.train(&mut agent, &mut env);
+
+	let mut state = env.reset();
+	for _ in 0..MAX_MOVES {
+		let step = env.step(agent.best_action(&state, &ACTIONS));
+		state = step.state;
+		if step.done {
+			break;
+		}
+	}
+	println!("DQN {} the game in {} moves with a score of {}, trained for {} timesteps",
+			 if state.has_won() { "won" } else { "didn't win" }, state.get_moves(), state.get_score(), trainer.timesteps());
+	if !state.has_won() {
+		process::exit(1);
+	}
+}
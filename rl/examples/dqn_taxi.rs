@@ -0,0 +1,104 @@
+//! Trains a deep Q-network on the taxi game from the grid the game draws of itself, then plays a set of
+//! games it hasn't seen without exploring. Run with `cargo run --release --example dqn_taxi`.
+
+extern crate rand;
+extern crate rl;
+extern crate taxi;
+
+use std::process;
+
+use rand::{Rng, XorShiftRng};
+
+use rl::dqn::{Dqn, DqnConfig, Observe};
+use rl::*;
+use taxi::reward::{ClassicTaxi, PotentialShaping};
+use taxi::{Dir, Game, GameConfig};
+
+const ACTIONS: [Dir; 4] = [Dir::Up, Dir::Right, Dir::Down, Dir::Left];
+const DISCOUNT: f64 = 0.95;
+const EPISODES: u64 = 3_000;
+const GAMES: u32 = 100;
+/// The longest game needs 21 moves when played perfectly
+const MAX_MOVES: u32 = 25;
+
+/// The taxi game where every game gets a new seed, the agent sees the whole game
+struct Taxi {
+	game: Game,
+	reward: PotentialShaping<ClassicTaxi>,
+	seeds: XorShiftRng,
+}
+
+impl Taxi {
+	fn new(seed: u32) -> Taxi {
+		Taxi {
+			game: Game::new(false),
+			reward: PotentialShaping::new(ClassicTaxi, DISCOUNT),
+			seeds: seeded_rng(seed),
+		}
+	}
+}
+
+impl Environment for Taxi {
+	type State = Game;
+	type Action = Dir;
+
+	fn actions(&self) -> Vec<Dir> {
+		ACTIONS.to_vec()
+	}
+	fn reset(&mut self) -> Game {
+		self.game = Game::with_config(GameConfig { seed: Some(self.seeds.gen()), ..GameConfig::default() }, false);
+		self.game.clone()
+	}
+	fn step(&mut self, action: Dir) -> Step<Game> {
+		let reward = self.game.step(action, &self.reward);
+		Step {
+			state: self.game.clone(),
+			reward,
+			done: self.game.is_over(),
+		}
+	}
+	fn won(&self) -> bool {
+		self.game.has_won()
+	}
+}
+
+/// Shows the network the grid of the first taxi
+struct Grid;
+
+impl Observe<Game> for Grid {
+	fn size(&self) -> usize {
+		let (rows, cols) = Game::new(false).world_size();
+		Game::GRID_PLANES * rows * cols
+	}
+	fn observe(&self, game: &Game) -> Vec<f32> {
+		game.grid(0)
+	}
+}
+
+fn main() {
+	let config = DqnConfig { hidden: vec![128], discount: DISCOUNT, ..DqnConfig::default() };
+	let mut agent = Dqn::new(Grid, ACTIONS.to_vec(), config, 1);
+	let mut env = Taxi::new(1);
+	let mut trainer = Trainer::new(Epsilon::Linear { start: 1.0, end: 0.05, steps: 50_000 }, Period::Episodes(EPISODES), 1);
+	trainer.max_episode_length = 200;
+	trainer.train(&mut agent, &mut env);
+
+	let mut evaluation = Taxi::new(2);
+	let mut moves = Vec::new();
+	for _ in 0..GAMES {
+		let mut state = evaluation.reset();
+		for m in 1..MAX_MOVES + 1 {
+			let step = evaluation.step(agent.best_action(&state, &ACTIONS));
+			if step.done {
+				moves.push(m);
+				break;
+			}
+			state = step.state;
+		}
+	}
+	println!("DQN won {}/{} games, {:.1} moves on average, trained for {} timesteps",
+			 moves.len(), GAMES, moves.iter().sum::<u32>() as f64 / moves.len().max(1) as f64, trainer.timesteps());
+	if moves.len() as u32 != GAMES {
+		process::exit(1);
+	}
+}
@@ -0,0 +1,470 @@
+//! Deep Q-networks, the Q-function is a small neural network that looks at an observation of the whole game.
+//! It doesn't need an entry for every state like a table or hand-made features like the agents in `linear`,
+//! so it can deal with maps it has never seen. Everything runs on the CPU, the networks are small enough for that.
+
+use std::collections::VecDeque;
+
+use rand::{Rng, XorShiftRng};
+
+use {seeded_rng, Agent, Exploration, Transition};
+
+/// Turns a state into the numbers the network looks at
+pub trait Observe<S> {
+	/// Number of values in an observation
+	fn size(&self) -> usize;
+
+	/// The observation of a state, always `size()` values long
+	fn observe(&self, state: &S) -> Vec<f32>;
+}
+
+/// A fully connected layer
+#[derive(Clone, Debug)]
+struct Layer {
+	outputs: usize,
+	/// Input by input, the weights to every output
+	weights: Vec<f32>,
+	biases: Vec<f32>,
+}
+
+impl Layer {
+	/// A layer with random weights scaled to the number of inputs, which suits ReLU networks
+	fn new(inputs: usize, outputs: usize, rng: &mut XorShiftRng) -> Layer {
+		let limit = (6.0 / inputs as f32).sqrt();
+		Layer {
+			outputs,
+			weights: (0..inputs * outputs).map(|_| (rng.next_f32() * 2.0 - 1.0) * limit).collect(),
+			biases: vec![0.0; outputs],
+		}
+	}
+
+	/// A layer of the same shape with everything set to 0
+	fn zeros(&self) -> Layer {
+		Layer {
+			outputs: self.outputs,
+			weights: vec![0.0; self.weights.len()],
+			biases: vec![0.0; self.outputs],
+		}
+	}
+
+	/// The weights from one input to every output
+	fn outgoing(&self, input: usize) -> &[f32] {
+		&self.weights[input * self.outputs..(input + 1) * self.outputs]
+	}
+
+	fn forward(&self, input: &[f32]) -> Vec<f32> {
+		let mut output = self.biases.clone();
+		// Observations of games are mostly zeroes, skipping them makes the first layer a lot faster
+		for (i, &x) in input.iter().enumerate().filter(|&(_, &x)| x != 0.0) {
+			for (o, &w) in output.iter_mut().zip(self.outgoing(i)) {
+				*o += w * x;
+			}
+		}
+		output
+	}
+}
+
+/// A multi-layer perceptron, with ReLU after every layer but the last
+#[derive(Clone, Debug)]
+pub struct Network {
+	layers: Vec<Layer>,
+}
+
+impl Network {
+	/// A network with random weights. `sizes` starts with the number of inputs, followed by the size of
+	/// every hidden layer and ends with the number of outputs
+	pub fn new(sizes: &[usize], rng: &mut XorShiftRng) -> Network {
+		assert!(sizes.len() >= 2, "A network needs at least a number of inputs and outputs");
+		Network {
+			layers: sizes.windows(2).map(|w| Layer::new(w[0], w[1], rng)).collect(),
+		}
+	}
+
+	/// The outputs of the network for an input
+	pub fn forward(&self, input: &[f32]) -> Vec<f32> {
+		self.activations(input).pop().unwrap()
+	}
+
+	/// The input of every layer followed by the output of the network
+	fn activations(&self, input: &[f32]) -> Vec<Vec<f32>> {
+		let mut activations = vec![input.to_vec()];
+		for (l, layer) in self.layers.iter().enumerate() {
+			let mut output = layer.forward(&activations[l]);
+			if l + 1 < self.layers.len() {
+				for x in &mut output {
+					*x = x.max(0.0);
+				}
+			}
+			activations.push(output);
+		}
+		activations
+	}
+
+	/// Adds the gradient of the weights to `gradient`, given the activations of a forward pass and the
+	/// gradient of the outputs
+	fn backward(&self, activations: &[Vec<f32>], mut delta: Vec<f32>, gradient: &mut Network) {
+		for (l, layer) in self.layers.iter().enumerate().rev() {
+			let input = &activations[l];
+			let g = &mut gradient.layers[l];
+			for (b, &d) in g.biases.iter_mut().zip(&delta) {
+				*b += d;
+			}
+			for (i, &x) in input.iter().enumerate().filter(|&(_, &x)| x != 0.0) {
+				for (w, &d) in g.weights[i * layer.outputs..(i + 1) * layer.outputs].iter_mut().zip(&delta) {
+					*w += x * d;
+				}
+			}
+			if l > 0 {
+				// Inputs of hidden layers went through a ReLU, so only the positive ones pass the gradient on
+				delta = input.iter().enumerate().map(|(i, &x)| {
+					if x > 0.0 {
+						layer.outgoing(i).iter().zip(&delta).map(|(w, d)| w * d).sum()
+					} else {
+						0.0
+					}
+				}).collect();
+			}
+		}
+	}
+
+	/// A network of the same shape with everything set to 0
+	fn zeros(&self) -> Network {
+		Network {
+			layers: self.layers.iter().map(Layer::zeros).collect(),
+		}
+	}
+}
+
+/// The Adam optimizer, keeps running averages of the gradient and its square for every weight
+#[derive(Clone, Debug)]
+struct Adam {
+	rate: f32,
+	mean: Network,
+	variance: Network,
+	steps: i32,
+}
+
+impl Adam {
+	const BETA1: f32 = 0.9;
+	const BETA2: f32 = 0.999;
+	const EPSILON: f32 = 1e-8;
+
+	fn new(rate: f32, network: &Network) -> Adam {
+		Adam {
+			rate,
+			mean: network.zeros(),
+			variance: network.zeros(),
+			steps: 0,
+		}
+	}
+
+	/// Moves the weights of the network against the gradient
+	fn step(&mut self, network: &mut Network, gradient: &Network) {
+		self.steps += 1;
+		let rate = self.rate * (1.0 - Adam::BETA2.powi(self.steps)).sqrt() / (1.0 - Adam::BETA1.powi(self.steps));
+		let layers = network.layers.iter_mut().zip(&gradient.layers).zip(self.mean.layers.iter_mut().zip(&mut self.variance.layers));
+		for ((layer, g), (mean, variance)) in layers {
+			Adam::update(rate, &mut layer.weights, &g.weights, &mut mean.weights, &mut variance.weights);
+			Adam::update(rate, &mut layer.biases, &g.biases, &mut mean.biases, &mut variance.biases);
+		}
+	}
+
+	fn update(rate: f32, weights: &mut [f32], gradient: &[f32], mean: &mut [f32], variance: &mut [f32]) {
+		for (((w, &g), m), v) in weights.iter_mut().zip(gradient).zip(mean.iter_mut()).zip(variance.iter_mut()) {
+			*m = Adam::BETA1 * *m + (1.0 - Adam::BETA1) * g;
+			*v = Adam::BETA2 * *v + (1.0 - Adam::BETA2) * g * g;
+			*w -= rate * *m / (v.sqrt() + Adam::EPSILON);
+		}
+	}
+}
+
+/// Settings for a `Dqn` agent
+#[derive(Clone, Debug, PartialEq)]
+pub struct DqnConfig {
+	/// Size of every hidden layer of the network
+	pub hidden: Vec<usize>,
+	/// Step size of the optimizer
+	pub learning_rate: f32,
+	pub discount: f64,
+	/// How many remembered steps to learn from at once
+	pub batch_size: usize,
+	/// How many of the most recent steps to remember
+	pub replay_capacity: usize,
+	/// Learning only starts once this many steps have been remembered
+	pub replay_start: usize,
+	/// Number of steps between every update of the network, at least 1
+	pub train_every: u64,
+	/// Number of steps between every copy of the network to the target network, at least 1
+	pub target_update: u64,
+	/// Let the network pick the next action and the target network tell how good it is (Double DQN),
+	/// which keeps the agent from being too optimistic
+	pub double: bool,
+}
+
+impl Default for DqnConfig {
+	fn default() -> DqnConfig {
+		DqnConfig {
+			hidden: vec![64],
+			learning_rate: 0.001,
+			discount: 0.99,
+			batch_size: 32,
+			replay_capacity: 50_000,
+			replay_start: 1_000,
+			train_every: 4,
+			target_update: 1_000,
+			double: true,
+		}
+	}
+}
+
+/// A step the agent remembers to learn from later
+#[derive(Clone, Debug)]
+struct Experience<S> {
+	state: S,
+	action: usize,
+	reward: f64,
+	next_state: S,
+	done: bool,
+}
+
+/// Deep Q-learning. The agent remembers the steps it takes and learns from random batches of them, with
+/// the targets coming from a copy of the network that is only updated every now and then to keep them steady.
+pub struct Dqn<S, A, O> {
+	pub observer: O,
+	pub config: DqnConfig,
+	pub network: Network,
+	target: Network,
+	optimizer: Adam,
+	/// The actions the network has an output for, in order
+	actions: Vec<A>,
+	replay: VecDeque<Experience<S>>,
+	steps: u64,
+}
+
+impl<S: Clone, A: Copy + PartialEq, O: Observe<S>> Dqn<S, A, O> {
+	/// An agent with a new random network, it has an output for every action in `actions`
+	pub fn new(observer: O, actions: Vec<A>, config: DqnConfig, seed: u32) -> Dqn<S, A, O> {
+		assert!(config.batch_size > 0, "A DQN needs to learn from at least one step at a time");
+		assert!(config.replay_capacity > 0, "A DQN needs room to remember at least one step");
+		assert!(config.train_every > 0 && config.target_update > 0, "A DQN needs at least one step between updates");
+		let mut sizes = vec![observer.size()];
+		sizes.extend(&config.hidden);
+		sizes.push(actions.len());
+		let network = Network::new(&sizes, &mut seeded_rng(seed));
+		Dqn {
+			target: network.clone(),
+			optimizer: Adam::new(config.learning_rate, &network),
+			network,
+			observer,
+			config,
+			actions,
+			replay: VecDeque::new(),
+			steps: 0,
+		}
+	}
+
+	/// Number of steps the agent has learned from
+	pub fn steps(&self) -> u64 {
+		self.steps
+	}
+
+	fn index(&self, action: A) -> usize {
+		self.actions.iter().position(|&a| a == action).expect("The network has no output for this action")
+	}
+
+	/// The output of the network for every action in `actions`
+	fn values(&self, state: &S, actions: &[A]) -> Vec<f64> {
+		let output = self.network.forward(&self.observer.observe(state));
+		actions.iter().map(|&a| output[self.index(a)] as f64).collect()
+	}
+
+	/// Updates the network from a random batch of remembered steps, returns the average size of their TD errors
+	fn train(&mut self, rng: &mut XorShiftRng) -> f64 {
+		let mut gradient = self.network.zeros();
+		let mut errors = 0.0;
+		for _ in 0..self.config.batch_size {
+			let experience = &self.replay[rng.gen_range(0, self.replay.len())];
+			let mut target = experience.reward;
+			if !experience.done {
+				let next = self.observer.observe(&experience.next_state);
+				let values = self.target.forward(&next);
+				let best = if self.config.double { argmax(&self.network.forward(&next)) } else { argmax(&values) };
+				target += self.config.discount * values[best] as f64;
+			}
+			let activations = self.network.activations(&self.observer.observe(&experience.state));
+			let error = activations.last().unwrap()[experience.action] as f64 - target;
+			errors += error.abs();
+			// The gradient of the Huber loss, errors bigger than 1 don't make the step any bigger
+			let error = error.clamp(-1.0, 1.0);
+			let mut delta = vec![0.0; self.actions.len()];
+			delta[experience.action] = error as f32 / self.config.batch_size as f32;
+			self.network.backward(&activations, delta, &mut gradient);
+		}
+		self.optimizer.step(&mut self.network, &gradient);
+		errors / self.config.batch_size as f64
+	}
+}
+
+/// Index of the highest value, the first one wins ties
+fn argmax(values: &[f32]) -> usize {
+	let mut best = 0;
+	for (i, &v) in values.iter().enumerate() {
+		if v > values[best] {
+			best = i;
+		}
+	}
+	best
+}
+
+impl<S: Clone, A: Copy + PartialEq, O: Observe<S>> Agent<S, A> for Dqn<S, A, O> {
+	fn value(&self, state: &S, action: A) -> f64 {
+		self.values(state, &[action])[0]
+	}
+
+	fn learn(&mut self, t: &Transition<S, A>, _: &Exploration<A>, rng: &mut XorShiftRng) -> Option<f64> {
+		let action = self.index(t.action);
+		if self.replay.len() == self.config.replay_capacity {
+			self.replay.pop_front();
+		}
+		self.replay.push_back(Experience {
+			state: t.state.clone(),
+			action,
+			reward: t.reward,
+			next_state: t.next_state.clone(),
+			done: t.done,
+		});
+		self.steps += 1;
+		let mut error = None;
+		if self.replay.len() >= self.config.replay_start && self.steps % self.config.train_every == 0 {
+			error = Some(self.train(rng));
+		}
+		if self.steps % self.config.target_update == 0 {
+			self.target = self.network.clone();
+		}
+		error
+	}
+
+	// Both of these only need a single pass through the network instead of one for every action
+	fn best_action(&self, state: &S, actions: &[A]) -> A {
+		let values = self.values(state, actions);
+		let mut best = 0;
+		for (i, &v) in values.iter().enumerate() {
+			if v > values[best] {
+				best = i;
+			}
+		}
+		actions[best]
+	}
+
+	fn best_value(&self, state: &S, actions: &[A]) -> f64 {
+		self.values(state, actions).into_iter().fold(f64::NEG_INFINITY, f64::max)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Identity;
+
+	impl Observe<f32> for Identity {
+		fn size(&self) -> usize {
+			1
+		}
+
+		fn observe(&self, state: &f32) -> Vec<f32> {
+			vec![*state]
+		}
+	}
+
+	fn dqn(config: DqnConfig) -> Dqn<f32, usize, Identity> {
+		Dqn::new(Identity, vec![0, 1], config, 1)
+	}
+
+	#[test]
+	#[should_panic(expected = "at least one step between updates")]
+	fn train_every_0_is_rejected() {
+		dqn(DqnConfig { train_every: 0, ..DqnConfig::default() });
+	}
+
+	#[test]
+	#[should_panic(expected = "at least one step between updates")]
+	fn target_update_0_is_rejected() {
+		dqn(DqnConfig { target_update: 0, ..DqnConfig::default() });
+	}
+
+	#[test]
+	#[should_panic(expected = "at least one step at a time")]
+	fn batch_size_0_is_rejected() {
+		dqn(DqnConfig { batch_size: 0, ..DqnConfig::default() });
+	}
+
+	#[test]
+	#[should_panic(expected = "remember at least one step")]
+	fn replay_capacity_0_is_rejected() {
+		dqn(DqnConfig { replay_capacity: 0, ..DqnConfig::default() });
+	}
+
+	/// The weights and biases of every layer, in order
+	fn parameters(network: &mut Network) -> Vec<&mut f32> {
+		network.layers.iter_mut().flat_map(|layer| layer.weights.iter_mut().chain(layer.biases.iter_mut())).collect()
+	}
+
+	/// A loss that weighs every output differently, so its gradient with respect to the outputs is `weights`
+	fn loss(network: &Network, input: &[f32], weights: &[f32]) -> f32 {
+		network.forward(input).iter().zip(weights).map(|(o, w)| o * w).sum()
+	}
+
+	#[test]
+	fn backward_matches_finite_differences() {
+		let mut network = Network::new(&[3, 5, 4, 2], &mut seeded_rng(1));
+		let (input, weights) = ([0.5, -1.0, 2.0], [1.0, -0.5]);
+		let mut gradient = network.zeros();
+		network.backward(&network.activations(&input), weights.to_vec(), &mut gradient);
+		let analytic = parameters(&mut gradient).into_iter().map(|g| *g).collect::<Vec<_>>();
+
+		let epsilon = 1e-3;
+		for (i, &expected) in analytic.iter().enumerate() {
+			let original = *parameters(&mut network)[i];
+			*parameters(&mut network)[i] = original + epsilon;
+			let above = loss(&network, &input, &weights);
+			*parameters(&mut network)[i] = original - epsilon;
+			let below = loss(&network, &input, &weights);
+			*parameters(&mut network)[i] = original;
+			let numeric = (above - below) / (2.0 * epsilon);
+			assert!((numeric - expected).abs() < 1e-2, "parameter {}: backward says {}, finite differences {}", i, expected, numeric);
+		}
+	}
+
+	#[test]
+	fn adam_starts_with_steps_of_the_learning_rate() {
+		let mut network = Network::new(&[2, 2], &mut seeded_rng(1));
+		let before = network.clone();
+		let mut gradient = network.zeros();
+		gradient.layers[0].weights = vec![0.5, -2.0, 0.0, 1e-3];
+		let mut adam = Adam::new(0.1, &network);
+		adam.step(&mut network, &gradient);
+		let moved = network.layers[0].weights.iter().zip(&before.layers[0].weights).map(|(a, b)| a - b).collect::<Vec<_>>();
+		let expected = [-0.1, 0.1, 0.0, -0.1];
+		assert!(moved.iter().zip(&expected).all(|(m, e)| (m - e).abs() < 1e-4), "moved {:?}", moved);
+		assert_eq!(network.layers[0].biases, before.layers[0].biases);
+	}
+
+	#[test]
+	fn training_fits_a_target() {
+		let mut network = Network::new(&[2, 8, 1], &mut seeded_rng(2));
+		let mut adam = Adam::new(0.01, &network);
+		let samples = [([1.0, 0.0], 1.0), ([0.0, 1.0], -1.0), ([1.0, 1.0], 0.5)];
+		let error = |network: &Network| samples.iter().map(|&(x, y)| (network.forward(&x)[0] - y).abs()).sum::<f32>();
+		let start = error(&network);
+		for _ in 0..500 {
+			let mut gradient = network.zeros();
+			for &(x, y) in &samples {
+				let activations = network.activations(&x);
+				let delta = vec![activations.last().unwrap()[0] - y];
+				network.backward(&activations, delta, &mut gradient);
+			}
+			adam.step(&mut network, &gradient);
+		}
+		assert!(error(&network) < start / 10.0, "error went from {} to {}", start, error(&network));
+	}
+}
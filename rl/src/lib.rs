@@ -0,0 +1,128 @@
+//! A small reinforcement learning library for the workshop games.
+//!
+//! Games are wrapped in an `Environment` that uses their own state and action types directly,
+//! for example `taxi::Dir` as the action. Agents learn a Q-function from the steps they take and
+//! a `Trainer` runs the episodes, exploring with an epsilon-greedy policy on a schedule.
+//! Most agents keep a table of Q-values, the ones in `linear` approximate it from features instead
+//! and the one in `dqn` with a neural network that looks at the whole game.
+
+extern crate game_rng;
+extern crate rand;
+
+pub mod dqn;
+pub mod linear;
+pub mod metrics;
+mod schedule;
+mod table;
+mod tabular;
+mod trainer;
+
+use std::hash::Hash;
+
+use rand::{Rng, XorShiftRng};
+
+pub use schedule::{Epsilon, Period};
+pub use table::QTable;
+pub use tabular::{QLearning, Sarsa, ExpectedSarsa, DoubleQLearning, NStepSarsa, MonteCarlo};
+pub use game_rng::seeded_rng;
+pub use trainer::Trainer;
+
+/// A game as seen by a learning agent
+pub trait Environment {
+	/// What the agent gets to see, it's used as a key in Q-tables
+	type State: Clone + Eq + Hash;
+	/// What the agent can do, every action is allowed in every state
+	type Action: Copy + Eq + Hash;
+
+	/// All the actions the agent can choose from
+	fn actions(&self) -> Vec<Self::Action>;
+	/// Starts a new episode and returns the first state
+	fn reset(&mut self) -> Self::State;
+	/// Takes an action and returns what happened
+	fn step(&mut self, action: Self::Action) -> Step<Self::State>;
+	/// Shows the current state of the game, does nothing unless the environment overrides it
+	fn render(&self) {}
+	/// Whether the episode that just ended was won, environments that don't know about winning always say no
+	fn won(&self) -> bool {
+		false
+	}
+}
+
+/// The result of taking an action in an environment
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step<S> {
+	/// The state the environment ended up in
+	pub state: S,
+	/// The reward for the action
+	pub reward: f64,
+	/// True if the episode is over
+	pub done: bool,
+}
+
+/// One step of an episode from the point of view of the agent
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition<S, A> {
+	pub state: S,
+	pub action: A,
+	pub reward: f64,
+	pub next_state: S,
+	/// The action the agent is going to take next, `None` if the episode is over
+	pub next_action: Option<A>,
+	/// True if `next_state` ends the episode
+	pub done: bool,
+}
+
+/// The epsilon-greedy policy an agent follows while learning
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exploration<'a, A: 'a> {
+	/// All the actions the agent can choose from
+	pub actions: &'a [A],
+	/// How often the agent acts randomly
+	pub epsilon: f64,
+}
+
+/// An agent that learns the value of actions
+pub trait Agent<S, A: Copy> {
+	/// How good the agent thinks taking `action` in `state` is
+	fn value(&self, state: &S, action: A) -> f64;
+
+	/// Learns from one step of an episode. Returns the average size of the TD errors of the updates it made,
+	/// or `None` if it's waiting to learn from the step later
+	fn learn(&mut self, transition: &Transition<S, A>, exploration: &Exploration<A>, rng: &mut XorShiftRng) -> Option<f64>;
+
+	/// Called when an episode is over, either because it finished or because it went on for too long.
+	/// Returns the average size of the TD errors of any updates it made, like `learn`
+	fn end_episode(&mut self) -> Option<f64> {
+		None
+	}
+
+	/// The action with the highest value, the first one wins ties
+	fn best_action(&self, state: &S, actions: &[A]) -> A {
+		let mut best = actions[0];
+		let mut best_value = self.value(state, best);
+		for &action in &actions[1..] {
+			let value = self.value(state, action);
+			if value > best_value {
+				best = action;
+				best_value = value;
+			}
+		}
+		best
+	}
+
+	/// The highest value of any action in `state`
+	fn best_value(&self, state: &S, actions: &[A]) -> f64 {
+		actions.iter().map(|&a| self.value(state, a)).fold(f64::NEG_INFINITY, f64::max)
+	}
+}
+
+/// Picks a random action with probability `epsilon` and the best one otherwise
+pub fn epsilon_greedy<S, A, G>(agent: &G, state: &S, actions: &[A], epsilon: f64, rng: &mut XorShiftRng) -> A
+	where A: Copy, G: Agent<S, A> + ?Sized
+{
+	if rng.next_f64() < epsilon {
+		*rng.choose(actions).unwrap()
+	} else {
+		agent.best_action(state, actions)
+	}
+}
@@ -0,0 +1,190 @@
+//! Agents that approximate the Q-function as a weighted sum of features instead of keeping a table,
+//! so what they learn in one state carries over to similar states they haven't seen.
+
+use rand::XorShiftRng;
+
+use {Agent, Exploration, Transition};
+
+/// Describes state-action pairs as a vector of numbers
+pub trait Features<S, A> {
+	/// Total number of features, the agent keeps one weight per feature
+	fn size(&self) -> usize;
+
+	/// The features of taking `action` in `state` as (index, value) pairs, anything that isn't listed is 0
+	fn features(&self, state: &S, action: A) -> Vec<(usize, f64)>;
+}
+
+/// Tile coding, covers a box of continuous space with several overlapping grids ("tilings") that are each shifted
+/// a bit. A point activates one tile per tiling, nearby points share most of their tiles so they generalize to
+/// each other, while points further away share fewer and fewer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileCoder {
+	/// The lowest and highest value of every dimension
+	ranges: Vec<(f64, f64)>,
+	/// Number of tiles along every dimension of a tiling
+	tiles: usize,
+	/// Number of tilings
+	tilings: usize,
+}
+
+impl TileCoder {
+	pub fn new(ranges: Vec<(f64, f64)>, tiles: usize, tilings: usize) -> TileCoder {
+		assert!(tiles > 0 && tilings > 0, "Tile coding needs at least one tile and one tiling");
+		TileCoder {
+			ranges,
+			tiles,
+			tilings,
+		}
+	}
+
+	/// Number of tilings, which is also the number of tiles a point falls in
+	pub fn tilings(&self) -> usize {
+		self.tilings
+	}
+
+	/// Number of tiles in total, tilings are shifted so each has an extra tile along every dimension
+	pub fn size(&self) -> usize {
+		self.tilings * (self.tiles + 1).pow(self.ranges.len() as u32)
+	}
+
+	/// The tiles a point falls in, one per tiling. Points outside of the ranges are clamped to the edges.
+	pub fn tiles(&self, point: &[f64]) -> Vec<usize> {
+		assert_eq!(point.len(), self.ranges.len(), "A point needs one value per dimension");
+		let per_tiling = (self.tiles + 1).pow(self.ranges.len() as u32);
+		(0..self.tilings).map(|tiling| {
+			let mut index = 0;
+			for (d, (&x, &(low, high))) in point.iter().zip(self.ranges.iter()).enumerate() {
+				let width = (high - low) / self.tiles as f64;
+				// Shifting every dimension by a different odd multiple keeps the tilings from lining up diagonally
+				let shift = tiling as f64 * (2 * d + 1) as f64 / self.tilings as f64 % 1.0;
+				let coordinate = ((x.max(low).min(high) - low) / width + shift).floor() as usize;
+				index = index * (self.tiles + 1) + coordinate.min(self.tiles);
+			}
+			tiling * per_tiling + index
+		}).collect()
+	}
+}
+
+/// Semi-gradient SARSA with a linear Q-function. Every step moves the weights of the active features
+/// towards the SARSA target, so `alpha` is usually divided by the number of features that are active at once.
+#[derive(Clone, Debug)]
+pub struct LinearSarsa<F> {
+	pub features: F,
+	pub weights: Vec<f64>,
+	pub alpha: f64,
+	pub discount: f64,
+}
+
+impl<F> LinearSarsa<F> {
+	/// An agent that starts with all weights at 0
+	pub fn new<S, A>(features: F, alpha: f64, discount: f64) -> LinearSarsa<F> where F: Features<S, A> {
+		LinearSarsa {
+			weights: vec![0.0; features.size()],
+			features,
+			alpha,
+			discount,
+		}
+	}
+}
+
+impl<S, A: Copy, F: Features<S, A>> Agent<S, A> for LinearSarsa<F> {
+	fn value(&self, state: &S, action: A) -> f64 {
+		self.features.features(state, action).iter().map(|&(i, x)| self.weights[i] * x).sum()
+	}
+
+	fn learn(&mut self, t: &Transition<S, A>, _: &Exploration<A>, _: &mut XorShiftRng) -> Option<f64> {
+		let future = match t.next_action {
+			Some(next) if !t.done => self.value(&t.next_state, next),
+			_ => 0.0,
+		};
+		let error = t.reward + self.discount * future - self.value(&t.state, t.action);
+		for (i, x) in self.features.features(&t.state, t.action) {
+			self.weights[i] += self.alpha * error * x;
+		}
+		Some(error.abs())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use seeded_rng;
+
+	#[test]
+	fn points_fall_in_one_tile_per_tiling() {
+		let coder = TileCoder::new(vec![(0.0, 10.0), (0.0, 10.0)], 5, 4);
+		assert_eq!(coder.size(), 4 * 6 * 6);
+		for &point in &[[0.0, 0.0], [3.3, 7.1], [10.0, 10.0]] {
+			let tiles = coder.tiles(&point);
+			assert_eq!(tiles.len(), coder.tilings());
+			for (tiling, &tile) in tiles.iter().enumerate() {
+				assert!(tile >= tiling * 36 && tile < (tiling + 1) * 36);
+			}
+		}
+	}
+
+	#[test]
+	fn nearby_points_share_more_tiles() {
+		let coder = TileCoder::new(vec![(0.0, 10.0)], 5, 4);
+		let shared = |a: f64, b: f64| coder.tiles(&[a]).iter().zip(coder.tiles(&[b])).filter(|&(x, y)| *x == y).count();
+		assert_eq!(shared(4.0, 4.0), 4);
+		assert!(shared(4.0, 4.2) > shared(4.0, 5.5));
+		assert_eq!(shared(1.0, 9.0), 0);
+	}
+
+	#[test]
+	fn points_outside_are_clamped() {
+		let coder = TileCoder::new(vec![(0.0, 10.0), (-1.0, 1.0)], 3, 2);
+		assert_eq!(coder.tiles(&[-5.0, 3.0]), coder.tiles(&[0.0, 1.0]));
+	}
+
+	/// One feature per state, so the agent is a table and the updates are easy to follow
+	struct OneHot;
+
+	impl Features<usize, usize> for OneHot {
+		fn size(&self) -> usize {
+			4
+		}
+		fn features(&self, state: &usize, action: usize) -> Vec<(usize, f64)> {
+			vec![(state * 2 + action, 1.0)]
+		}
+	}
+
+	#[test]
+	fn sarsa_moves_the_active_weights() {
+		let mut agent = LinearSarsa::new(OneHot, 0.5, 0.9);
+		agent.weights[3] = 10.0;
+		let exploration = Exploration { actions: &[0, 1], epsilon: 0.1 };
+		let step = Transition { state: 0, action: 0, reward: 1.0, next_state: 1, next_action: Some(1), done: false };
+		assert_eq!(agent.learn(&step, &exploration, &mut seeded_rng(1)), Some(10.0));
+		assert_eq!(agent.weights, vec![5.0, 0.0, 0.0, 10.0]);
+		let last = Transition { state: 1, action: 1, reward: 2.0, next_state: 0, next_action: None, done: true };
+		agent.learn(&last, &exploration, &mut seeded_rng(1));
+		assert_eq!(agent.value(&1, 1), 6.0);
+	}
+
+	#[test]
+	fn sarsa_generalizes_between_shared_features() {
+		let coder = TileCoder::new(vec![(0.0, 10.0)], 5, 4);
+		let mut agent = LinearSarsa::new(Tiles(coder), 1.0, 0.9);
+		let exploration = Exploration { actions: &[()], epsilon: 0.0 };
+		let step = Transition { state: 4.0, action: (), reward: 1.0, next_state: 4.0, next_action: None, done: true };
+		for _ in 0..50 {
+			agent.learn(&step, &exploration, &mut seeded_rng(1));
+		}
+		assert!((agent.value(&4.0, ()) - 1.0).abs() < 0.01);
+		assert!(agent.value(&4.2, ()) > agent.value(&5.5, ()));
+		assert_eq!(agent.value(&9.0, ()), 0.0);
+	}
+
+	struct Tiles(TileCoder);
+
+	impl Features<f64, ()> for Tiles {
+		fn size(&self) -> usize {
+			self.0.size()
+		}
+		fn features(&self, state: &f64, _: ()) -> Vec<(usize, f64)> {
+			self.0.tiles(&[*state]).into_iter().map(|tile| (tile, 0.25)).collect()
+		}
+	}
+}
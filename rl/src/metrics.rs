@@ -0,0 +1,203 @@
+//! What happened during training, episode by episode.
+//!
+//! The `Trainer` measures every episode, can write the measurements to a file as CSV or JSON lines
+//! for plotting learning curves and can print rolling averages while it trains.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Everything the trainer measured about one episode
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpisodeMetrics {
+	/// Number of the episode, the first one is 1
+	pub episode: u64,
+	/// Timesteps trained so far, this episode included
+	pub timesteps: u64,
+	/// Sum of all rewards in the episode
+	pub total_reward: f64,
+	/// Number of steps in the episode
+	pub length: u64,
+	pub won: bool,
+	/// Epsilon at the end of the episode
+	pub epsilon: f64,
+	/// Average size of the TD errors of the agent's updates, `None` if it didn't update anything
+	pub td_error: Option<f64>,
+}
+
+impl EpisodeMetrics {
+	/// The first line of a CSV file with metrics
+	pub const CSV_HEADER: &'static str = "episode,timesteps,return,length,won,epsilon,td_error";
+
+	/// The metrics as a line of CSV, in the order of `CSV_HEADER`. A missing TD error is left empty
+	pub fn to_csv(&self) -> String {
+		format!("{},{},{},{},{},{},{}", self.episode, self.timesteps, self.total_reward, self.length,
+				if self.won { 1 } else { 0 }, self.epsilon, self.td_error.map(|e| e.to_string()).unwrap_or_default())
+	}
+
+	/// The metrics as a JSON object on a single line, with the same names as the CSV columns
+	pub fn to_json(&self) -> String {
+		format!("{{\"episode\":{},\"timesteps\":{},\"return\":{},\"length\":{},\"won\":{},\"epsilon\":{},\"td_error\":{}}}",
+				self.episode, self.timesteps, json_number(self.total_reward), self.length, self.won,
+				json_number(self.epsilon), self.td_error.map(json_number).unwrap_or_else(|| "null".to_string()))
+	}
+}
+
+/// JSON has no infinity or NaN, they're written as null
+fn json_number(x: f64) -> String {
+	if x.is_finite() { x.to_string() } else { "null".to_string() }
+}
+
+/// How metrics are written to a file
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+	/// Comma separated values with a header
+	Csv,
+	/// A JSON object per line
+	JsonLines,
+}
+
+impl Format {
+	/// JSON lines for files ending in `.json` or `.jsonl`, CSV for everything else
+	pub fn from_path<P: AsRef<Path>>(path: P) -> Format {
+		match path.as_ref().extension().and_then(|e| e.to_str()) {
+			Some("json") | Some("jsonl") => Format::JsonLines,
+			_ => Format::Csv,
+		}
+	}
+}
+
+/// A file the trainer writes the metrics of every episode to
+pub struct MetricsLog {
+	out: BufWriter<File>,
+	format: Format,
+}
+
+impl MetricsLog {
+	/// Creates the file, replacing it if it already exists
+	pub fn create<P: AsRef<Path>>(path: P, format: Format) -> io::Result<MetricsLog> {
+		let mut out = BufWriter::new(File::create(path)?);
+		if format == Format::Csv {
+			writeln!(out, "{}", EpisodeMetrics::CSV_HEADER)?;
+		}
+		Ok(MetricsLog { out, format })
+	}
+
+	pub fn write(&mut self, metrics: &EpisodeMetrics) -> io::Result<()> {
+		let line = match self.format {
+			Format::Csv => metrics.to_csv(),
+			Format::JsonLines => metrics.to_json(),
+		};
+		writeln!(self.out, "{}", line)
+	}
+
+	pub fn flush(&mut self) -> io::Result<()> {
+		self.out.flush()
+	}
+}
+
+/// Averages over a number of episodes, usually the last few
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+	/// The last episode that was averaged over
+	pub episode: u64,
+	pub timesteps: u64,
+	pub total_reward: f64,
+	pub length: f64,
+	/// Fraction of the episodes that were won
+	pub win_rate: f64,
+	pub epsilon: f64,
+	/// Average over the episodes that had a TD error, `None` if none of them had one
+	pub td_error: Option<f64>,
+}
+
+impl Summary {
+	/// Averages the given episodes, `None` if there aren't any
+	pub fn of(episodes: &[EpisodeMetrics]) -> Option<Summary> {
+		let last = episodes.last()?;
+		let n = episodes.len() as f64;
+		let errors = episodes.iter().filter_map(|m| m.td_error).collect::<Vec<_>>();
+		Some(Summary {
+			episode: last.episode,
+			timesteps: last.timesteps,
+			total_reward: episodes.iter().map(|m| m.total_reward).sum::<f64>() / n,
+			length: episodes.iter().map(|m| m.length as f64).sum::<f64>() / n,
+			win_rate: episodes.iter().filter(|m| m.won).count() as f64 / n,
+			epsilon: last.epsilon,
+			td_error: if errors.is_empty() { None } else { Some(errors.iter().sum::<f64>() / errors.len() as f64) },
+		})
+	}
+
+	/// Prints the summary on a single line
+	pub fn print(&self) {
+		print!("episode {:>7} | timesteps {:>9} | return {:>8.2} | length {:>7.1} | won {:>5.1}% | epsilon {:.3}",
+			   self.episode, self.timesteps, self.total_reward, self.length, self.win_rate * 100.0, self.epsilon);
+		match self.td_error {
+			Some(error) => println!(" | TD error {:.4}", error),
+			None => println!(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn episode(episode: u64, total_reward: f64, won: bool, td_error: Option<f64>) -> EpisodeMetrics {
+		EpisodeMetrics {
+			episode,
+			timesteps: episode * 10,
+			total_reward,
+			length: 10 + episode,
+			won,
+			epsilon: 0.5,
+			td_error,
+		}
+	}
+
+	#[test]
+	fn csv_rows_follow_the_header() {
+		assert_eq!(EpisodeMetrics::CSV_HEADER.split(',').count(), episode(1, 1.0, true, None).to_csv().split(',').count());
+		assert_eq!(episode(1, -2.5, true, Some(0.25)).to_csv(), "1,10,-2.5,11,1,0.5,0.25");
+		assert_eq!(episode(2, 3.0, false, None).to_csv(), "2,20,3,12,0,0.5,");
+	}
+
+	#[test]
+	fn json_has_the_csv_names() {
+		assert_eq!(episode(1, -2.5, true, Some(0.25)).to_json(),
+				   "{\"episode\":1,\"timesteps\":10,\"return\":-2.5,\"length\":11,\"won\":true,\"epsilon\":0.5,\"td_error\":0.25}");
+	}
+
+	#[test]
+	fn json_writes_null_for_missing_and_non_finite_values() {
+		let json = episode(1, f64::NAN, false, None).to_json();
+		assert!(json.contains("\"return\":null") && json.contains("\"td_error\":null"), "{}", json);
+		let mut metrics = episode(1, f64::NEG_INFINITY, false, Some(f64::INFINITY));
+		metrics.epsilon = f64::NAN;
+		assert_eq!(metrics.to_json().matches("null").count(), 3, "{}", metrics.to_json());
+	}
+
+	#[test]
+	fn summary_averages_the_episodes() {
+		let episodes = [episode(1, 1.0, true, None), episode(2, 2.0, false, Some(1.0)), episode(3, 6.0, true, Some(0.5))];
+		assert_eq!(Summary::of(&episodes), Some(Summary {
+			episode: 3,
+			timesteps: 30,
+			total_reward: 3.0,
+			length: 12.0,
+			win_rate: 2.0 / 3.0,
+			epsilon: 0.5,
+			td_error: Some(0.75),
+		}));
+		assert_eq!(Summary::of(&episodes[..1]).unwrap().td_error, None);
+		assert_eq!(Summary::of(&[]), None);
+	}
+
+	#[test]
+	fn format_follows_the_extension() {
+		assert_eq!(Format::from_path("metrics.jsonl"), Format::JsonLines);
+		assert_eq!(Format::from_path("metrics.json"), Format::JsonLines);
+		assert_eq!(Format::from_path("metrics.csv"), Format::Csv);
+		assert_eq!(Format::from_path("metrics"), Format::Csv);
+	}
+}
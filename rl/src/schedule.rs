@@ -0,0 +1,74 @@
+/// How often an agent acts randomly while training, as a function of how many steps it has taken so far
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Epsilon {
+	/// Always the same
+	Constant(f64),
+	/// Goes from `start` to `end` in a straight line over `steps` steps, then stays at `end`
+	Linear { start: f64, end: f64, steps: u64 },
+	/// Gets multiplied by `decay` every step, but never goes below `end`
+	Exponential { start: f64, end: f64, decay: f64 },
+}
+
+impl Epsilon {
+	/// The value of epsilon after `step` steps
+	pub fn value(&self, step: u64) -> f64 {
+		match *self {
+			Epsilon::Constant(epsilon) => epsilon,
+			Epsilon::Linear { start, end, steps } => {
+				if step >= steps {
+					end
+				} else {
+					start + (end - start) * step as f64 / steps as f64
+				}
+			}
+			Epsilon::Exponential { start, end, decay } => (start * decay.powf(step as f64)).max(end),
+		}
+	}
+}
+
+/// How long to train for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Period {
+	Episodes(u64),
+	Timesteps(u64),
+}
+
+impl Period {
+	/// True once training has gone on for long enough
+	pub fn is_over(&self, episodes: u64, timesteps: u64) -> bool {
+		match *self {
+			Period::Episodes(n) => episodes >= n,
+			Period::Timesteps(n) => timesteps >= n,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn linear_goes_straight_to_end() {
+		let epsilon = Epsilon::Linear { start: 1.0, end: 0.2, steps: 100 };
+		assert_eq!(epsilon.value(0), 1.0);
+		assert!((epsilon.value(50) - 0.6).abs() < 1e-12);
+		assert_eq!(epsilon.value(100), 0.2);
+		assert_eq!(epsilon.value(1000), 0.2);
+	}
+
+	#[test]
+	fn exponential_stops_at_end() {
+		let epsilon = Epsilon::Exponential { start: 1.0, end: 0.1, decay: 0.5 };
+		assert_eq!(epsilon.value(2), 0.25);
+		assert_eq!(epsilon.value(10), 0.1);
+		assert_eq!(Epsilon::Constant(0.3).value(1000), 0.3);
+	}
+
+	#[test]
+	fn periods_count_the_right_thing() {
+		assert!(!Period::Episodes(10).is_over(9, 1000));
+		assert!(Period::Episodes(10).is_over(10, 0));
+		assert!(!Period::Timesteps(10).is_over(1000, 9));
+		assert!(Period::Timesteps(10).is_over(0, 10));
+	}
+}
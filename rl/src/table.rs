@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Iter;
+use std::hash::Hash;
+
+/// Values of state-action pairs, anything that hasn't been set yet has the initial value
+#[derive(Clone, Debug, PartialEq)]
+pub struct QTable<S: Eq + Hash, A: Eq + Hash> {
+	values: HashMap<(S, A), f64>,
+	initial: f64,
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> QTable<S, A> {
+	/// An empty table where everything starts at 0
+	pub fn new() -> QTable<S, A> {
+		QTable::with_initial(0.0)
+	}
+
+	/// An empty table where everything starts at `initial`, an optimistic value makes agents explore more
+	pub fn with_initial(initial: f64) -> QTable<S, A> {
+		QTable {
+			values: HashMap::new(),
+			initial,
+		}
+	}
+
+	/// The value of taking `action` in `state`
+	pub fn get(&self, state: &S, action: A) -> f64 {
+		self.values.get(&(state.clone(), action)).cloned().unwrap_or(self.initial)
+	}
+
+	/// Sets the value of taking `action` in `state`
+	pub fn set(&mut self, state: &S, action: A, value: f64) {
+		self.values.insert((state.clone(), action), value);
+	}
+
+	/// Moves the value of taking `action` in `state` a step of size `alpha` towards `target`,
+	/// returns how far off it was before the update (the TD error)
+	pub fn update(&mut self, state: &S, action: A, target: f64, alpha: f64) -> f64 {
+		let initial = self.initial;
+		let value = self.values.entry((state.clone(), action)).or_insert(initial);
+		let error = target - *value;
+		*value += alpha * error;
+		error
+	}
+
+	/// The action with the highest value in `state` and its value, the first one wins ties
+	pub fn best(&self, state: &S, actions: &[A]) -> (A, f64) {
+		let mut best = (actions[0], self.get(state, actions[0]));
+		for &action in &actions[1..] {
+			let value = self.get(state, action);
+			if value > best.1 {
+				best = (action, value);
+			}
+		}
+		best
+	}
+
+	/// Number of state-action pairs that have been set
+	pub fn len(&self) -> usize {
+		self.values.len()
+	}
+
+	/// True if nothing has been set yet
+	pub fn is_empty(&self) -> bool {
+		self.values.is_empty()
+	}
+
+	/// All the state-action pairs that have been set, with their values
+	pub fn iter(&self) -> Iter<'_, (S, A), f64> {
+		self.values.iter()
+	}
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> Default for QTable<S, A> {
+	fn default() -> QTable<S, A> {
+		QTable::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn unset_values_are_initial() {
+		let table = QTable::<u32, u8>::with_initial(2.5);
+		assert_eq!(table.get(&3, 1), 2.5);
+		assert!(table.is_empty());
+	}
+
+	#[test]
+	fn update_steps_towards_target() {
+		let mut table = QTable::new();
+		table.set(&0, 'a', 1.0);
+		assert_eq!(table.update(&0, 'a', 3.0, 0.5), 2.0);
+		assert_eq!(table.get(&0, 'a'), 2.0);
+		assert_eq!(table.update(&1, 'a', 4.0, 0.25), 4.0);
+		assert_eq!(table.get(&1, 'a'), 1.0);
+		assert_eq!(table.len(), 2);
+	}
+
+	#[test]
+	fn best_picks_the_first_of_ties() {
+		let mut table = QTable::new();
+		table.set(&0, 'b', 1.0);
+		table.set(&0, 'c', 1.0);
+		assert_eq!(table.best(&0, &['a', 'b', 'c']), ('b', 1.0));
+		assert_eq!(table.best(&1, &['a', 'b', 'c']), ('a', 0.0));
+	}
+}
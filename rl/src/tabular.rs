@@ -0,0 +1,350 @@
+//! Agents that keep their Q-function in a table, one value per state-action pair they have seen.
+//! They all learn with a step size `alpha` and discount future rewards with `discount`.
+
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+use rand::{Rng, XorShiftRng};
+
+use {Agent, Exploration, QTable, Transition};
+
+/// Q-learning, learns the value of the greedy policy no matter how the agent explores
+#[derive(Clone, Debug)]
+pub struct QLearning<S: Eq + Hash, A: Eq + Hash> {
+	pub table: QTable<S, A>,
+	pub alpha: f64,
+	pub discount: f64,
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> QLearning<S, A> {
+	pub fn new(alpha: f64, discount: f64) -> QLearning<S, A> {
+		QLearning {
+			table: QTable::new(),
+			alpha,
+			discount,
+		}
+	}
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> Agent<S, A> for QLearning<S, A> {
+	fn value(&self, state: &S, action: A) -> f64 {
+		self.table.get(state, action)
+	}
+
+	fn learn(&mut self, t: &Transition<S, A>, exploration: &Exploration<A>, _: &mut XorShiftRng) -> Option<f64> {
+		let future = if t.done { 0.0 } else { self.table.best(&t.next_state, exploration.actions).1 };
+		Some(self.table.update(&t.state, t.action, t.reward + self.discount * future, self.alpha).abs())
+	}
+}
+
+/// SARSA, learns the value of the policy the agent actually follows, exploration included
+#[derive(Clone, Debug)]
+pub struct Sarsa<S: Eq + Hash, A: Eq + Hash> {
+	pub table: QTable<S, A>,
+	pub alpha: f64,
+	pub discount: f64,
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> Sarsa<S, A> {
+	pub fn new(alpha: f64, discount: f64) -> Sarsa<S, A> {
+		Sarsa {
+			table: QTable::new(),
+			alpha,
+			discount,
+		}
+	}
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> Agent<S, A> for Sarsa<S, A> {
+	fn value(&self, state: &S, action: A) -> f64 {
+		self.table.get(state, action)
+	}
+
+	fn learn(&mut self, t: &Transition<S, A>, _: &Exploration<A>, _: &mut XorShiftRng) -> Option<f64> {
+		let future = match t.next_action {
+			Some(next) if !t.done => self.table.get(&t.next_state, next),
+			_ => 0.0,
+		};
+		Some(self.table.update(&t.state, t.action, t.reward + self.discount * future, self.alpha).abs())
+	}
+}
+
+/// Expected SARSA, like SARSA but averages over every action the policy could take next
+/// instead of using the one it happened to pick, which makes it less noisy
+#[derive(Clone, Debug)]
+pub struct ExpectedSarsa<S: Eq + Hash, A: Eq + Hash> {
+	pub table: QTable<S, A>,
+	pub alpha: f64,
+	pub discount: f64,
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> ExpectedSarsa<S, A> {
+	pub fn new(alpha: f64, discount: f64) -> ExpectedSarsa<S, A> {
+		ExpectedSarsa {
+			table: QTable::new(),
+			alpha,
+			discount,
+		}
+	}
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> Agent<S, A> for ExpectedSarsa<S, A> {
+	fn value(&self, state: &S, action: A) -> f64 {
+		self.table.get(state, action)
+	}
+
+	fn learn(&mut self, t: &Transition<S, A>, exploration: &Exploration<A>, _: &mut XorShiftRng) -> Option<f64> {
+		let future = if t.done {
+			0.0
+		} else {
+			let actions = exploration.actions;
+			let mean = actions.iter().map(|&a| self.table.get(&t.next_state, a)).sum::<f64>() / actions.len() as f64;
+			let best = self.table.best(&t.next_state, actions).1;
+			exploration.epsilon * mean + (1.0 - exploration.epsilon) * best
+		};
+		Some(self.table.update(&t.state, t.action, t.reward + self.discount * future, self.alpha).abs())
+	}
+}
+
+/// Double Q-learning, keeps two tables and uses one to pick the best next action and the other to value it.
+/// This avoids the overestimation Q-learning gets from taking the max over noisy values.
+#[derive(Clone, Debug)]
+pub struct DoubleQLearning<S: Eq + Hash, A: Eq + Hash> {
+	pub first: QTable<S, A>,
+	pub second: QTable<S, A>,
+	pub alpha: f64,
+	pub discount: f64,
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> DoubleQLearning<S, A> {
+	pub fn new(alpha: f64, discount: f64) -> DoubleQLearning<S, A> {
+		DoubleQLearning {
+			first: QTable::new(),
+			second: QTable::new(),
+			alpha,
+			discount,
+		}
+	}
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> Agent<S, A> for DoubleQLearning<S, A> {
+	/// The average of both tables
+	fn value(&self, state: &S, action: A) -> f64 {
+		(self.first.get(state, action) + self.second.get(state, action)) / 2.0
+	}
+
+	fn learn(&mut self, t: &Transition<S, A>, exploration: &Exploration<A>, rng: &mut XorShiftRng) -> Option<f64> {
+		let (update, evaluate) = if rng.gen() {
+			(&mut self.first, &self.second)
+		} else {
+			(&mut self.second, &self.first)
+		};
+		let future = if t.done {
+			0.0
+		} else {
+			let next = update.best(&t.next_state, exploration.actions).0;
+			evaluate.get(&t.next_state, next)
+		};
+		Some(update.update(&t.state, t.action, t.reward + self.discount * future, self.alpha).abs())
+	}
+}
+
+/// n-step SARSA, looks `n` rewards ahead before relying on its own estimate,
+/// which spreads rewards back through an episode faster than one-step methods
+#[derive(Clone, Debug)]
+pub struct NStepSarsa<S: Eq + Hash, A: Eq + Hash> {
+	pub table: QTable<S, A>,
+	pub alpha: f64,
+	pub discount: f64,
+	pub n: usize,
+	/// The last (up to) `n` states, actions and rewards that haven't been learned from yet
+	pending: VecDeque<(S, A, f64)>,
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> NStepSarsa<S, A> {
+	pub fn new(n: usize, alpha: f64, discount: f64) -> NStepSarsa<S, A> {
+		assert!(n > 0, "n-step SARSA needs to look at least one step ahead");
+		NStepSarsa {
+			table: QTable::new(),
+			alpha,
+			discount,
+			n,
+			pending: VecDeque::new(),
+		}
+	}
+
+	/// Learns from the oldest pending step, using the rewards after it and optionally a value to bootstrap from
+	fn learn_oldest(&mut self, bootstrap: f64) -> f64 {
+		let mut target = bootstrap;
+		for &(_, _, reward) in self.pending.iter().rev() {
+			target = reward + self.discount * target;
+		}
+		let (state, action, _) = self.pending.pop_front().unwrap();
+		self.table.update(&state, action, target, self.alpha)
+	}
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> Agent<S, A> for NStepSarsa<S, A> {
+	fn value(&self, state: &S, action: A) -> f64 {
+		self.table.get(state, action)
+	}
+
+	fn learn(&mut self, t: &Transition<S, A>, _: &Exploration<A>, _: &mut XorShiftRng) -> Option<f64> {
+		self.pending.push_back((t.state.clone(), t.action, t.reward));
+		match t.next_action {
+			Some(next) if !t.done => {
+				if self.pending.len() == self.n {
+					let bootstrap = self.table.get(&t.next_state, next);
+					Some(self.learn_oldest(bootstrap).abs())
+				} else {
+					None
+				}
+			}
+			_ => {
+				let mut errors = Vec::with_capacity(self.pending.len());
+				while !self.pending.is_empty() {
+					errors.push(self.learn_oldest(0.0));
+				}
+				mean_size(&errors)
+			}
+		}
+	}
+
+	/// Steps that are still pending when an episode gets cut short can't be learned from properly, so they're dropped
+	fn end_episode(&mut self) -> Option<f64> {
+		self.pending.clear();
+		None
+	}
+}
+
+/// Monte Carlo control, waits for the end of an episode and learns from the rewards that actually came after
+/// every step instead of relying on its own estimates
+#[derive(Clone, Debug)]
+pub struct MonteCarlo<S: Eq + Hash, A: Eq + Hash> {
+	pub table: QTable<S, A>,
+	pub alpha: f64,
+	pub discount: f64,
+	/// Everything that happened so far in the current episode
+	episode: Vec<(S, A, f64)>,
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> MonteCarlo<S, A> {
+	pub fn new(alpha: f64, discount: f64) -> MonteCarlo<S, A> {
+		MonteCarlo {
+			table: QTable::new(),
+			alpha,
+			discount,
+			episode: Vec::new(),
+		}
+	}
+}
+
+impl<S: Clone + Eq + Hash, A: Copy + Eq + Hash> Agent<S, A> for MonteCarlo<S, A> {
+	fn value(&self, state: &S, action: A) -> f64 {
+		self.table.get(state, action)
+	}
+
+	fn learn(&mut self, t: &Transition<S, A>, _: &Exploration<A>, _: &mut XorShiftRng) -> Option<f64> {
+		self.episode.push((t.state.clone(), t.action, t.reward));
+		None
+	}
+
+	/// Learns from every step of the episode, from the last one back to the first
+	fn end_episode(&mut self) -> Option<f64> {
+		let mut target = 0.0;
+		let mut errors = Vec::with_capacity(self.episode.len());
+		for (state, action, reward) in self.episode.drain(..).rev() {
+			target = reward + self.discount * target;
+			errors.push(self.table.update(&state, action, target, self.alpha));
+		}
+		mean_size(&errors)
+	}
+}
+
+/// The average size of some TD errors, `None` if there aren't any
+fn mean_size(errors: &[f64]) -> Option<f64> {
+	if errors.is_empty() {
+		None
+	} else {
+		Some(errors.iter().map(|e| e.abs()).sum::<f64>() / errors.len() as f64)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use seeded_rng;
+
+	const ACTIONS: [char; 2] = ['a', 'b'];
+
+	fn step(state: u32, action: char, reward: f64, next_action: Option<char>) -> Transition<u32, char> {
+		Transition { state, action, reward, next_state: state + 1, next_action, done: next_action.is_none() }
+	}
+
+	fn learn<G: Agent<u32, char>>(agent: &mut G, transition: &Transition<u32, char>, epsilon: f64) -> Option<f64> {
+		agent.learn(transition, &Exploration { actions: &ACTIONS, epsilon }, &mut seeded_rng(1))
+	}
+
+	#[test]
+	fn q_learning_bootstraps_from_the_best_action() {
+		let mut agent = QLearning::new(0.5, 0.9);
+		agent.table.set(&1, 'b', 10.0);
+		assert_eq!(learn(&mut agent, &step(0, 'a', 1.0, Some('a')), 0.1), Some(10.0));
+		assert_eq!(agent.value(&0, 'a'), 5.0);
+		// Nothing comes after the end of an episode
+		assert_eq!(learn(&mut agent, &step(0, 'b', 1.0, None), 0.1), Some(1.0));
+		assert_eq!(agent.value(&0, 'b'), 0.5);
+	}
+
+	#[test]
+	fn sarsa_bootstraps_from_the_next_action() {
+		let mut agent = Sarsa::new(0.5, 0.9);
+		agent.table.set(&1, 'b', 10.0);
+		learn(&mut agent, &step(0, 'a', 1.0, Some('a')), 0.1);
+		assert_eq!(agent.value(&0, 'a'), 0.5);
+		learn(&mut agent, &step(0, 'b', 1.0, Some('b')), 0.1);
+		assert_eq!(agent.value(&0, 'b'), 5.0);
+	}
+
+	#[test]
+	fn expected_sarsa_weighs_in_exploration() {
+		let mut agent = ExpectedSarsa::new(1.0, 1.0);
+		agent.table.set(&1, 'b', 10.0);
+		// Half the time random (mean 5), half the time greedy (10)
+		learn(&mut agent, &step(0, 'a', 0.0, Some('a')), 0.5);
+		assert_eq!(agent.value(&0, 'a'), 7.5);
+	}
+
+	#[test]
+	fn double_q_learning_updates_one_table() {
+		let mut agent = DoubleQLearning::new(1.0, 0.9);
+		learn(&mut agent, &step(0, 'a', 2.0, None), 0.1);
+		assert_eq!(agent.first.get(&0, 'a') + agent.second.get(&0, 'a'), 2.0);
+		assert_eq!(agent.value(&0, 'a'), 1.0);
+	}
+
+	#[test]
+	fn n_step_sarsa_waits_for_n_rewards() {
+		let mut agent = NStepSarsa::new(2, 1.0, 0.5);
+		agent.table.set(&2, 'a', 8.0);
+		assert_eq!(learn(&mut agent, &step(0, 'a', 1.0, Some('a')), 0.1), None);
+		learn(&mut agent, &step(1, 'a', 2.0, Some('a')), 0.1);
+		assert_eq!(agent.value(&0, 'a'), 1.0 + 0.5 * 2.0 + 0.25 * 8.0);
+		// The end of the episode flushes everything that's still pending
+		learn(&mut agent, &step(2, 'a', 4.0, None), 0.1);
+		assert_eq!(agent.value(&1, 'a'), 2.0 + 0.5 * 4.0);
+		assert_eq!(agent.value(&2, 'a'), 4.0);
+	}
+
+	#[test]
+	fn monte_carlo_learns_at_the_end() {
+		let mut agent = MonteCarlo::new(1.0, 0.5);
+		assert_eq!(learn(&mut agent, &step(0, 'a', 1.0, Some('a')), 0.1), None);
+		assert_eq!(learn(&mut agent, &step(1, 'a', 2.0, None), 0.1), None);
+		assert_eq!(agent.value(&0, 'a'), 0.0);
+		assert!(agent.end_episode().is_some());
+		assert_eq!(agent.value(&0, 'a'), 2.0);
+		assert_eq!(agent.value(&1, 'a'), 2.0);
+		assert_eq!(agent.end_episode(), None);
+	}
+}
@@ -0,0 +1,206 @@
+use game_rng::seeded_rng;
+use rand::XorShiftRng;
+
+use metrics::{EpisodeMetrics, MetricsLog, Summary};
+use {epsilon_greedy, Agent, Environment, Epsilon, Exploration, Period, Transition};
+
+/// Runs episodes of an environment and lets an agent learn from them
+pub struct Trainer {
+	/// How often the agent explores, as a function of the number of timesteps so far
+	pub epsilon: Epsilon,
+	/// How long to train for
+	pub period: Period,
+	/// Episodes that go on for longer than this are cut short
+	pub max_episode_length: u64,
+	/// Where to write the metrics of every episode, if anywhere
+	pub log: Option<MetricsLog>,
+	/// Print the averages of the last this many episodes every this many episodes, 0 prints nothing
+	pub summary_every: u64,
+	rng: XorShiftRng,
+	episodes: u64,
+	timesteps: u64,
+	/// One entry for every episode, this only ever grows
+	metrics: Vec<EpisodeMetrics>,
+}
+
+impl Trainer {
+	/// A trainer that makes all of its random choices from the given seed
+	pub fn new(epsilon: Epsilon, period: Period, seed: u32) -> Trainer {
+		Trainer {
+			epsilon,
+			period,
+			max_episode_length: 10_000,
+			log: None,
+			summary_every: 0,
+			rng: seeded_rng(seed),
+			episodes: 0,
+			timesteps: 0,
+			metrics: Vec::new(),
+		}
+	}
+
+	/// Number of episodes trained so far
+	pub fn episodes(&self) -> u64 {
+		self.episodes
+	}
+
+	/// Number of timesteps trained so far
+	pub fn timesteps(&self) -> u64 {
+		self.timesteps
+	}
+
+	/// The metrics of every episode trained so far.
+	/// They're all kept in memory, there's no limit on how many, at 64 bytes an episode
+	pub fn metrics(&self) -> &[EpisodeMetrics] {
+		&self.metrics
+	}
+
+	/// The random number generator the trainer explores with
+	pub fn rng(&mut self) -> &mut XorShiftRng {
+		&mut self.rng
+	}
+
+	/// Trains the agent until the training period is over
+	pub fn train<E, G>(&mut self, agent: &mut G, env: &mut E)
+		where E: Environment, G: Agent<E::State, E::Action>
+	{
+		let actions = env.actions();
+		while !self.period.is_over(self.episodes, self.timesteps) {
+			self.run_episode(agent, env, &actions);
+		}
+		if let Some(Err(e)) = self.log.as_mut().map(MetricsLog::flush) {
+			println!("Couldn't write training metrics: {}", e);
+		}
+	}
+
+	/// Plays a single episode while learning from it
+	fn run_episode<E, G>(&mut self, agent: &mut G, env: &mut E, actions: &[E::Action])
+		where E: Environment, G: Agent<E::State, E::Action>
+	{
+		let mut state = env.reset();
+		let mut exploration = Exploration {
+			actions,
+			epsilon: self.epsilon.value(self.timesteps),
+		};
+		let mut action = epsilon_greedy(agent, &state, actions, exploration.epsilon, &mut self.rng);
+		let mut total_reward = 0.0;
+		let mut length = 0;
+		let mut errors = Vec::new();
+		for _ in 0..self.max_episode_length {
+			let step = env.step(action);
+			self.timesteps += 1;
+			total_reward += step.reward;
+			length += 1;
+			exploration.epsilon = self.epsilon.value(self.timesteps);
+			let next_action = if step.done {
+				None
+			} else {
+				Some(epsilon_greedy(agent, &step.state, actions, exploration.epsilon, &mut self.rng))
+			};
+			let transition = Transition {
+				state,
+				action,
+				reward: step.reward,
+				next_state: step.state,
+				next_action,
+				done: step.done,
+			};
+			errors.extend(agent.learn(&transition, &exploration, &mut self.rng));
+			match next_action {
+				Some(next) => {
+					state = transition.next_state;
+					action = next;
+				}
+				None => break,
+			}
+		}
+		errors.extend(agent.end_episode());
+		self.episodes += 1;
+		self.record(EpisodeMetrics {
+			episode: self.episodes,
+			timesteps: self.timesteps,
+			total_reward,
+			length,
+			won: env.won(),
+			epsilon: exploration.epsilon,
+			td_error: if errors.is_empty() { None } else { Some(errors.iter().sum::<f64>() / errors.len() as f64) },
+		});
+	}
+
+	/// Keeps the metrics of an episode, writes them to the log and prints a summary when it's time for one
+	fn record(&mut self, metrics: EpisodeMetrics) {
+		if let Some(Err(e)) = self.log.as_mut().map(|log| log.write(&metrics)) {
+			println!("Couldn't write training metrics, stopped logging them: {}", e);
+			self.log = None;
+		}
+		self.metrics.push(metrics);
+		if self.summary_every > 0 && self.episodes % self.summary_every == 0 {
+			let window = self.metrics.len().saturating_sub(self.summary_every as usize);
+			if let Some(summary) = Summary::of(&self.metrics[window..]) {
+				summary.print();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use {QLearning, Step};
+
+	/// A corridor of 5 cells, going right from the middle to the end wins, going left to the start loses
+	struct Corridor(i32);
+
+	impl Environment for Corridor {
+		type State = i32;
+		type Action = i32;
+
+		fn actions(&self) -> Vec<i32> {
+			vec![-1, 1]
+		}
+		fn reset(&mut self) -> i32 {
+			self.0 = 2;
+			self.0
+		}
+		fn step(&mut self, action: i32) -> Step<i32> {
+			self.0 += action;
+			Step { state: self.0, reward: if self.0 == 4 { 1.0 } else { 0.0 }, done: self.0 == 0 || self.0 == 4 }
+		}
+		fn won(&self) -> bool {
+			self.0 == 4
+		}
+	}
+
+	fn train(seed: u32) -> (Trainer, QLearning<i32, i32>) {
+		let mut agent = QLearning::new(0.5, 0.9);
+		let mut trainer = Trainer::new(Epsilon::Constant(0.5), Period::Episodes(200), seed);
+		trainer.train(&mut agent, &mut Corridor(2));
+		(trainer, agent)
+	}
+
+	#[test]
+	fn learns_to_walk_the_corridor() {
+		let (trainer, agent) = train(1);
+		assert_eq!(trainer.episodes(), 200);
+		assert_eq!(trainer.timesteps(), trainer.metrics().iter().map(|m| m.length).sum::<u64>());
+		assert!((1..4).all(|cell| agent.best_action(&cell, &[-1, 1]) == 1));
+		assert!(trainer.metrics().iter().any(|m| m.won));
+	}
+
+	#[test]
+	fn same_seed_trains_the_same() {
+		assert_eq!(train(3).0.metrics(), train(3).0.metrics());
+		assert!(train(3).0.metrics() != train(4).0.metrics());
+	}
+
+	#[test]
+	fn long_episodes_are_cut_short() {
+		let mut agent = QLearning::new(0.5, 0.9);
+		let mut trainer = Trainer::new(Epsilon::Constant(0.5), Period::Timesteps(10), 1);
+		// The middle of the corridor is 2 steps from either end
+		trainer.max_episode_length = 1;
+		trainer.train(&mut agent, &mut Corridor(2));
+		assert_eq!(trainer.episodes(), 10);
+		assert!(trainer.metrics().iter().all(|m| m.length == 1 && !m.won));
+	}
+}
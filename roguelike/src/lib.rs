@@ -7,8 +7,10 @@ use ansi_term::Colour::{White, Red, Yellow, Blue, Green, Black, Cyan};
 
 static GOLD_VALUE: u32 = 30;
 static ENEMY_VALUE: u32 = 20;
-static ROW_SIZE: usize = 19;
-static COL_SIZE: usize = 30;
+pub static ROW_SIZE: usize = 19;
+pub static COL_SIZE: usize = 30;
+/// Number of planes in `Game::grid`
+pub static GRID_PLANES: usize = 8;
 
 /// Helper function to clear the terminal screen, not tested on Windows
 fn clear_screen() {
@@ -150,6 +152,45 @@ impl Game {
 		((((1 - self.position.0 as i32).pow(2) + (21 - self.position.1 as i32).pow(2)) as f64).sqrt())
 	}
 
+	/// The game as a stack of `GRID_PLANES` planes the size of the map, for agents that learn from the
+	/// whole picture. Plane by plane and row by row, a cell is 1 if it has
+	/// 0. a wall
+	/// 1. an enemy
+	/// 2. gold
+	/// 3. the sword
+	/// 4. the goal
+	/// 5. the player
+	/// 6. the enemy the player just attacked, one more hit kills it
+	///
+	/// and 0 otherwise. Every cell of the last plane is 1 if the player has the sword.
+	pub fn grid(&self) -> Vec<f32> {
+		let cells = ROW_SIZE * COL_SIZE;
+		let mut grid = vec![0.0; GRID_PLANES * cells];
+		for (r, row) in self.world.iter().enumerate() {
+			for (c, object) in row.iter().enumerate() {
+				let plane = match *object {
+					Object::Wall => 0,
+					Object::Enemy => 1,
+					Object::Gold => 2,
+					Object::Sword => 3,
+					Object::Goal => 4,
+					Object::Empty => continue,
+				};
+				grid[plane * cells + r * COL_SIZE + c] = 1.0;
+			}
+		}
+		grid[5 * cells + self.position.0 * COL_SIZE + self.position.1] = 1.0;
+		if let Action::AttackedEnemy(r, c) = self.action {
+			grid[6 * cells + r * COL_SIZE + c] = 1.0;
+		}
+		if self.has_sword {
+			for cell in &mut grid[7 * cells..] {
+				*cell = 1.0;
+			}
+		}
+		grid
+	}
+
 	/// Enter move and optionally print the map, returning whether or not this move won the game
 	pub fn enter_move(&mut self, dir: &Dir, print: bool) -> bool {
 		self.make_move(&dir);
@@ -0,0 +1,46 @@
+//! Trains on a single layout of the taxi game and then plays every other layout without exploring.
+//! A Q-table has never seen those states and has nothing to go on, the linear agent's features still
+//! point it the right way. Run with `cargo run --release --example held_out`.
+
+extern crate rl;
+extern crate taxi;
+extern crate taxi_learning_bot as bot;
+
+use std::process;
+
+use rl::linear::LinearSarsa;
+use rl::*;
+use taxi::Dir;
+
+use bot::features::{layouts, LayoutGame, TaxiFeatures, View};
+
+const DISCOUNT: f64 = 0.95;
+const EPISODES: u64 = 5_000;
+/// Enough for the longest layout when played perfectly, with a bit to spare
+const MAX_MOVES: u32 = 30;
+
+/// Trains an agent on the first layout and returns how many of the other layouts it wins
+fn check<G: Agent<View, Dir>>(name: &str, mut agent: G) -> usize {
+	let layouts = layouts();
+	let mut env = LayoutGame::new(layouts[..1].to_vec(), DISCOUNT, 1);
+	let epsilon = Epsilon::Linear { start: 1.0, end: 0.05, steps: EPISODES * 20 };
+	let mut trainer = Trainer::new(epsilon, Period::Episodes(EPISODES), 1);
+	trainer.train(&mut agent, &mut env);
+
+	let trained = env.play_greedy(&agent, layouts[0], MAX_MOVES);
+	let moves = layouts[1..].iter().filter_map(|&layout| env.play_greedy(&agent, layout, MAX_MOVES)).collect::<Vec<_>>();
+	println!("{:<14} training layout {}, won {:>2}/{} held-out layouts, {:.1} moves on average",
+			 name, if trained.is_some() { "won" } else { "lost" }, moves.len(), layouts.len() - 1,
+			 moves.iter().sum::<u32>() as f64 / moves.len().max(1) as f64);
+	moves.len()
+}
+
+fn main() {
+	let features = TaxiFeatures::new();
+	let alpha = 0.1 / features.active() as f64;
+	check("Q-table", QLearning::new(0.5, DISCOUNT));
+	let won = check("Linear SARSA", LinearSarsa::new(features, alpha, DISCOUNT));
+	if won < layouts().len() - 1 {
+		process::exit(1);
+	}
+}
@@ -0,0 +1,428 @@
+//! Trains the reference agent with many combinations of parameters and ranks them by how well they play.
+//!
+//! The parameters come from a spec file with a `name = values` line per parameter, for example
+//!
+//! ```text
+//! search = grid
+//! epsilon = 0.25, 0.5, 0.75
+//! learning-rate = 0.5, 0.9
+//! discount = 0.9, 0.95
+//! episodes = 5000
+//! seeds = 1, 2, 3
+//! ```
+//!
+//! A grid search tries every combination of the listed values. With `search = random` the parameters
+//! can also be ranges like `0.1..0.9`, and `samples` combinations are drawn from them. Every combination
+//! is trained once for every seed and then plays `games` games without exploring.
+
+extern crate rand;
+extern crate rl;
+extern crate taxi_learning_bot as bot;
+
+use std::cmp::Ordering;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use rand::Rng;
+
+use rl::{seeded_rng, Epsilon, Period, QLearning, Trainer};
+
+use bot::GameState;
+
+/// Values to try for one parameter
+#[derive(Clone, Debug, PartialEq)]
+enum Values {
+	List(Vec<f64>),
+	/// Anything between the two, only for random search
+	Range(f64, f64),
+}
+
+impl Values {
+	fn parse(text: &str) -> Result<Values, String> {
+		let number = |s: &str| s.trim().parse::<f64>().map_err(|_| format!("'{}' is not a number", s.trim()));
+		if let Some(i) = text.find("..") {
+			return Ok(Values::Range(number(&text[..i])?, number(&text[i + 2..])?));
+		}
+		text.split(',').map(number).collect::<Result<Vec<_>, _>>().map(Values::List)
+	}
+
+	fn pick<R: Rng>(&self, rng: &mut R) -> f64 {
+		match *self {
+			Values::List(ref values) => *rng.choose(values).unwrap(),
+			Values::Range(low, high) => low + rng.next_f64() * (high - low),
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Search {
+	Grid,
+	/// Draw this many combinations at random
+	Random(usize),
+}
+
+/// What to sweep over, read from a spec file
+#[derive(Clone, Debug)]
+struct Spec {
+	search: Search,
+	epsilon: Values,
+	learning_rate: Values,
+	discount: Values,
+	/// Number of training episodes, or timesteps if `timesteps` is set
+	length: Values,
+	timesteps: bool,
+	seeds: Vec<u32>,
+	/// Games to play with every trained agent
+	games: u32,
+	/// Games that take longer than this count as lost
+	max_moves: u32,
+}
+
+impl Spec {
+	fn read(path: &str) -> Result<Spec, String> {
+		let text = fs::read_to_string(path).map_err(|e| format!("Couldn't read {}: {}", path, e))?;
+		Spec::parse(&text)
+	}
+
+	fn parse(text: &str) -> Result<Spec, String> {
+		let mut spec = Spec {
+			search: Search::Grid,
+			epsilon: Values::List(vec![0.75]),
+			learning_rate: Values::List(vec![0.9]),
+			discount: Values::List(vec![0.9]),
+			length: Values::List(vec![5000.0]),
+			timesteps: false,
+			seeds: vec![0],
+			games: 100,
+			max_moves: 25,
+		};
+		let mut samples = 20;
+		let mut random = false;
+		for (n, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap().trim();
+			if line.is_empty() {
+				continue;
+			}
+			let (name, value) = match line.find('=') {
+				Some(i) => (line[..i].trim(), line[i + 1..].trim()),
+				None => return Err(format!("Line {}: expected `name = values`", n + 1)),
+			};
+			let error = |e: String| format!("Line {}: {}", n + 1, e);
+			match name {
+				"search" => random = match value {
+					"grid" => false,
+					"random" => true,
+					_ => return Err(error(format!("unknown search '{}', use grid or random", value))),
+				},
+				"samples" => samples = count(value).map_err(error)?,
+				"epsilon" => spec.epsilon = Values::parse(value).map_err(error)?,
+				"learning-rate" => spec.learning_rate = Values::parse(value).map_err(error)?,
+				"discount" => spec.discount = Values::parse(value).map_err(error)?,
+				"episodes" | "timesteps" => {
+					spec.length = Values::parse(value).map_err(error)?;
+					let shortest = match spec.length {
+						Values::List(ref values) => values.iter().cloned().fold(f64::INFINITY, f64::min),
+						Values::Range(low, high) => low.min(high),
+					};
+					if shortest.round() < 1.0 {
+						return Err(error(format!("{} have to be at least 1", name)));
+					}
+					spec.timesteps = name == "timesteps";
+				}
+				"seeds" => spec.seeds = value.split(',').map(|s| whole(s.trim())).collect::<Result<_, _>>().map_err(error)?,
+				"games" => spec.games = count(value).map_err(error)?,
+				"max-moves" => spec.max_moves = count(value).map_err(error)?,
+				_ => return Err(error(format!("unknown parameter '{}'", name))),
+			}
+		}
+		if random {
+			spec.search = Search::Random(samples);
+		} else if [&spec.epsilon, &spec.learning_rate, &spec.discount, &spec.length].iter().any(|v| matches!(**v, Values::Range(..))) {
+			return Err("Ranges only work with `search = random`".to_string());
+		}
+		Ok(spec)
+	}
+
+	/// Every combination of parameters to train with
+	fn combinations(&self) -> Vec<Params> {
+		match self.search {
+			Search::Random(samples) => {
+				let mut rng = seeded_rng(self.seeds[0]);
+				(0..samples).map(|_| Params {
+					epsilon: self.epsilon.pick(&mut rng),
+					learning_rate: self.learning_rate.pick(&mut rng),
+					discount: self.discount.pick(&mut rng),
+					length: self.length.pick(&mut rng).round() as u64,
+				}).collect()
+			}
+			Search::Grid => {
+				let list = |values: &Values| match *values {
+					Values::List(ref values) => values.clone(),
+					Values::Range(..) => unreachable!(),
+				};
+				let mut combinations = Vec::new();
+				for &epsilon in &list(&self.epsilon) {
+					for &learning_rate in &list(&self.learning_rate) {
+						for &discount in &list(&self.discount) {
+							for &length in &list(&self.length) {
+								combinations.push(Params { epsilon, learning_rate, discount, length: length.round() as u64 });
+							}
+						}
+					}
+				}
+				combinations
+			}
+		}
+	}
+}
+
+fn whole<T: FromStr>(value: &str) -> Result<T, String> {
+	value.parse().map_err(|_| format!("'{}' is not a whole number", value))
+}
+
+/// A whole number that's at least 1
+fn count<T: FromStr + Default + PartialEq>(value: &str) -> Result<T, String> {
+	let n = whole(value)?;
+	if n == T::default() {
+		return Err(format!("'{}' has to be at least 1", value));
+	}
+	Ok(n)
+}
+
+/// One combination of parameters
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Params {
+	epsilon: f64,
+	learning_rate: f64,
+	discount: f64,
+	length: u64,
+}
+
+/// How an agent trained with some parameters and seed did, `None` if training or playing panicked
+struct Outcome {
+	params: usize,
+	seed: u32,
+	result: Option<(u32, u32)>,
+}
+
+/// Trains an agent and plays the evaluation games with it
+fn run(spec: &Spec, params: Params, seed: u32) -> (u32, u32) {
+	let period = if spec.timesteps { Period::Timesteps(params.length) } else { Period::Episodes(params.length) };
+	let mut agent = QLearning::new(params.learning_rate, params.discount);
+	let mut trainer = Trainer::new(Epsilon::Constant(params.epsilon), period, seed);
+	trainer.train(&mut agent, &mut GameState::new(params.discount, seed));
+
+	// The evaluation games are the same for everyone, and different from all the training games
+	let mut evaluation = GameState::new(params.discount, u32::MAX);
+	let moves = (0..spec.games).filter_map(|_| evaluation.play_greedy(&agent, spec.max_moves)).collect::<Vec<_>>();
+	(moves.len() as u32, moves.iter().sum())
+}
+
+/// The results for one combination of parameters, over all seeds
+struct Row {
+	params: Params,
+	win_rate: f64,
+	/// Fraction of games won by the worst seed
+	worst: f64,
+	/// Average moves of the games that were won, infinite if none were
+	moves: f64,
+}
+
+fn usage() -> ! {
+	println!("Usage: sweep SPEC [--threads N] [--output FILE]");
+	println!();
+	println!("  --threads  number of training jobs to run at once, at least 1 (default: one per CPU core)");
+	println!("  --output   also write the ranked results to a CSV file");
+	process::exit(1)
+}
+
+fn main() {
+	let mut args = env::args().skip(1);
+	let path = args.next().unwrap_or_else(|| usage());
+	let mut threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+	let mut output = None;
+	while let Some(flag) = args.next() {
+		let value = args.next().unwrap_or_else(|| usage());
+		match flag.as_str() {
+			"--threads" => threads = value.parse().ok().filter(|&n| n > 0).unwrap_or_else(|| usage()),
+			"--output" => output = Some(value),
+			_ => usage(),
+		}
+	}
+	let spec = Spec::read(&path).unwrap_or_else(|e| {
+		println!("{}", e);
+		process::exit(1)
+	});
+	let combinations = spec.combinations();
+
+	let jobs = combinations.iter().enumerate()
+		.flat_map(|(i, &params)| spec.seeds.iter().map(move |&seed| (i, params, seed)))
+		.collect::<Vec<_>>();
+	let total = jobs.len();
+	println!("Training {} combinations with {} seeds each on {} threads", combinations.len(), spec.seeds.len(), threads);
+
+	let jobs = Arc::new(Mutex::new(jobs));
+	let (results, outcomes) = mpsc::channel();
+	let workers = (0..threads).map(|_| {
+		let (jobs, results, spec) = (jobs.clone(), results.clone(), spec.clone());
+		thread::spawn(move || loop {
+			let job = jobs.lock().unwrap().pop();
+			match job {
+				Some((i, params, seed)) => {
+					// A panicking job shouldn't take the worker down with it, the others still have to run
+					let result = panic::catch_unwind(AssertUnwindSafe(|| run(&spec, params, seed))).ok();
+					results.send(Outcome { params: i, seed, result }).unwrap();
+				}
+				None => break,
+			}
+		})
+	}).collect::<Vec<_>>();
+	drop(results);
+
+	let mut won = vec![Vec::new(); combinations.len()];
+	let mut moves = vec![0; combinations.len()];
+	let mut failed = Vec::new();
+	for (done, outcome) in outcomes.iter().enumerate() {
+		match outcome.result {
+			Some((w, m)) => {
+				won[outcome.params].push(w);
+				moves[outcome.params] += m;
+			}
+			None => failed.push((outcome.params, outcome.seed)),
+		}
+		print!("\r{}/{} jobs done", done + 1, total);
+		io::stdout().flush().unwrap();
+	}
+	println!();
+	for worker in workers {
+		if worker.join().is_err() {
+			println!("A worker thread panicked, some jobs didn't run");
+			process::exit(1);
+		}
+	}
+	if !failed.is_empty() {
+		for (i, seed) in failed {
+			let params = combinations[i];
+			println!("Training failed with epsilon {}, learning-rate {}, discount {}, {} {} and seed {}", params.epsilon,
+					 params.learning_rate, params.discount, params.length, if spec.timesteps { "timesteps" } else { "episodes" }, seed);
+		}
+		process::exit(1);
+	}
+
+	let games = spec.games as f64;
+	let mut rows = combinations.iter().enumerate().map(|(i, &params)| {
+		let total_won = won[i].iter().sum::<u32>();
+		Row {
+			params,
+			win_rate: total_won as f64 / (games * won[i].len() as f64),
+			worst: *won[i].iter().min().unwrap() as f64 / games,
+			moves: if total_won > 0 { moves[i] as f64 / total_won as f64 } else { f64::INFINITY },
+		}
+	}).collect::<Vec<_>>();
+	// Most games won first, then the fewest moves
+	rows.sort_by(|a, b| {
+		let order = |x: f64, y: f64| x.partial_cmp(&y).unwrap_or(Ordering::Equal);
+		order(b.win_rate, a.win_rate).then(order(a.moves, b.moves))
+	});
+
+	let length = if spec.timesteps { "timesteps" } else { "episodes" };
+	println!("{:>4}  {:>7}  {:>13}  {:>8}  {:>9}  {:>8}  {:>10}  {:>5}", "rank", "epsilon", "learning-rate",
+			 "discount", length, "won", "worst seed", "moves");
+	for (rank, row) in rows.iter().enumerate() {
+		println!("{:>4}  {:>7.3}  {:>13.3}  {:>8.3}  {:>9}  {:>7.1}%  {:>9.1}%  {:>5.1}", rank + 1, row.params.epsilon,
+				 row.params.learning_rate, row.params.discount, row.params.length, row.win_rate * 100.0,
+				 row.worst * 100.0, row.moves);
+	}
+
+	if let Some(path) = output {
+		if let Err(e) = write_csv(&path, &rows, length) {
+			println!("Couldn't write results to {}: {}", path, e);
+		}
+	}
+}
+
+fn write_csv(path: &str, rows: &[Row], length: &str) -> io::Result<()> {
+	let mut out = File::create(path)?;
+	writeln!(out, "rank,epsilon,learning_rate,discount,{},win_rate,worst_seed,moves", length)?;
+	for (rank, row) in rows.iter().enumerate() {
+		writeln!(out, "{},{},{},{},{},{},{},{}", rank + 1, row.params.epsilon, row.params.learning_rate,
+				 row.params.discount, row.params.length, row.win_rate, row.worst, row.moves)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn error(text: &str) -> String {
+		Spec::parse(text).unwrap_err()
+	}
+
+	#[test]
+	fn defaults_without_any_lines() {
+		let spec = Spec::parse("# nothing but a comment\n\n").unwrap();
+		assert_eq!(spec.search, Search::Grid);
+		assert_eq!((spec.seeds.clone(), spec.games, spec.max_moves, spec.timesteps), (vec![0], 100, 25, false));
+		assert_eq!(spec.combinations().len(), 1);
+	}
+
+	#[test]
+	fn grid_tries_every_combination() {
+		let spec = Spec::parse("search = grid\nepsilon = 0.25, 0.5, 0.75\nlearning-rate = 0.5,0.9\n\
+								discount = 0.9, 0.95 # two of them\ntimesteps = 1000\nseeds = 1, 2, 3\ngames = 10").unwrap();
+		assert_eq!(spec.learning_rate, Values::List(vec![0.5, 0.9]));
+		assert_eq!((spec.seeds.clone(), spec.games, spec.timesteps), (vec![1, 2, 3], 10, true));
+		let combinations = spec.combinations();
+		assert_eq!(combinations.len(), 12);
+		assert_eq!(combinations[0], Params { epsilon: 0.25, learning_rate: 0.5, discount: 0.9, length: 1000 });
+		assert_eq!(combinations[11], Params { epsilon: 0.75, learning_rate: 0.9, discount: 0.95, length: 1000 });
+	}
+
+	#[test]
+	fn random_draws_samples_from_ranges() {
+		let spec = Spec::parse("search = random\nsamples = 7\nepsilon = 0.1..0.9\ndiscount = 0.9, 0.99\nepisodes = 100..200").unwrap();
+		assert_eq!(spec.search, Search::Random(7));
+		assert_eq!(spec.epsilon, Values::Range(0.1, 0.9));
+		let combinations = spec.combinations();
+		assert_eq!(combinations.len(), 7);
+		for params in &combinations {
+			assert!(params.epsilon >= 0.1 && params.epsilon < 0.9, "{:?}", params);
+			assert!(params.discount == 0.9 || params.discount == 0.99, "{:?}", params);
+			assert!(params.length >= 100 && params.length <= 200, "{:?}", params);
+		}
+		assert_eq!(combinations, spec.combinations(), "Random search should be seeded");
+	}
+
+	#[test]
+	fn unknown_names_are_rejected() {
+		assert_eq!(error("epsilon = 0.5\nalpha = 0.5"), "Line 2: unknown parameter 'alpha'");
+		assert_eq!(error("search = bayesian"), "Line 1: unknown search 'bayesian', use grid or random");
+	}
+
+	#[test]
+	fn bad_values_are_rejected() {
+		assert_eq!(error("epsilon"), "Line 1: expected `name = values`");
+		assert_eq!(error("epsilon = 0.5, lots"), "Line 1: 'lots' is not a number");
+		assert_eq!(error("discount = 0.5..x"), "Line 1: 'x' is not a number");
+		assert_eq!(error("seeds = 1, -2"), "Line 1: '-2' is not a whole number");
+		assert_eq!(error("games = 1.5"), "Line 1: '1.5' is not a whole number");
+		assert_eq!(error("epsilon = 0.1..0.9"), "Ranges only work with `search = random`");
+	}
+
+	#[test]
+	fn zero_counts_are_rejected() {
+		assert_eq!(error("games = 0"), "Line 1: '0' has to be at least 1");
+		assert_eq!(error("max-moves = 0"), "Line 1: '0' has to be at least 1");
+		assert_eq!(error("search = random\nsamples = 0"), "Line 2: '0' has to be at least 1");
+		assert_eq!(error("episodes = 100, 0"), "Line 1: episodes have to be at least 1");
+		assert_eq!(error("search = random\ntimesteps = 0..100"), "Line 2: timesteps have to be at least 1");
+	}
+}
@@ -0,0 +1,213 @@
+//! Hand-made features of the taxi game for agents that approximate the Q-function, see `rl::linear`.
+//!
+//! Instead of a number for every possible state the agent sees where it is and where it has to go next,
+//! which stays meaningful when the passenger and the goal are somewhere it has never seen them before.
+
+use rand::{Rng, XorShiftRng};
+
+use rl::linear::{Features, TileCoder};
+use rl::{seeded_rng, Agent, Environment, Step};
+use taxi::*;
+use taxi::reward::{ClassicTaxi, PotentialShaping};
+
+use ACTIONS;
+
+/// A pickup and a destination
+pub type Layout = ((u32, u32), (u32, u32));
+
+/// Every combination of the spots passengers show up at and want to go to
+pub fn layouts() -> Vec<Layout> {
+	PASSENGER_SPOTS.iter().flat_map(|&pickup| GOAL_SPOTS.iter().map(move |&goal| (pickup, goal))).collect()
+}
+
+/// What the agent knows about the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct View {
+	pub position: (u32, u32),
+	pub passenger: (u32, u32),
+	pub goal: (u32, u32),
+	pub picked_up: bool,
+}
+
+impl View {
+	pub fn of(game: &Game) -> View {
+		View {
+			position: game.player_position(),
+			passenger: game.passenger_position(),
+			goal: game.goal_position(),
+			picked_up: game.passenger_picked_up(),
+		}
+	}
+
+	/// Rows and columns from the taxi to where it has to go next, the passenger or the goal
+	pub fn offset(&self) -> (f64, f64) {
+		let target = if self.picked_up { self.goal } else { self.passenger };
+		(target.0 as f64 - self.position.0 as f64, target.1 as f64 - self.position.1 as f64)
+	}
+}
+
+/// A few features describe how good the state is whatever the agent does: a bias, the picked-up flag and
+/// how many rows and columns away the next target is, the passenger or the goal. Others get their own
+/// weights for every action: which way the target is and where the taxi is, tile coded so the agent can
+/// learn about the walls. None of them care whether the target is the passenger or the goal,
+/// so heading for one teaches the agent how to head for the other.
+#[derive(Clone, Debug)]
+pub struct TaxiFeatures {
+	positions: TileCoder,
+	/// How much the position counts compared to the direction of the target
+	position_weight: f64,
+}
+
+/// Number of features that are shared by all actions
+const SHARED: usize = 4;
+/// The target is behind, level with or ahead of the taxi, along the rows and along the columns
+const SIGNS: usize = 3;
+const AXES: usize = 2;
+
+impl TaxiFeatures {
+	pub fn new() -> TaxiFeatures {
+		TaxiFeatures {
+			positions: TileCoder::new(vec![(0.0, 11.0), (0.0, 11.0)], 4, 4),
+			position_weight: 0.3,
+		}
+	}
+
+	/// Size of the block of weights for each action
+	fn block(&self) -> usize {
+		AXES * SIGNS + self.positions.size()
+	}
+
+	/// Number of features that are active at once, the learning rate should be about `1 / active()` of
+	/// what it would be for a table
+	pub fn active(&self) -> usize {
+		SHARED + AXES + self.positions.tilings()
+	}
+}
+
+impl Default for TaxiFeatures {
+	fn default() -> TaxiFeatures {
+		TaxiFeatures::new()
+	}
+}
+
+impl Features<View, Dir> for TaxiFeatures {
+	fn size(&self) -> usize {
+		SHARED + ACTIONS.len() * self.block()
+	}
+
+	fn features(&self, view: &View, action: Dir) -> Vec<(usize, f64)> {
+		let (rows, cols) = view.offset();
+		let mut features = vec![
+			(0, 1.0),
+			(1, if view.picked_up { 1.0 } else { 0.0 }),
+			(2, rows.abs() / 10.0),
+			(3, cols.abs() / 10.0),
+		];
+		let start = SHARED + action as usize * self.block();
+		let sign = |x: f64| if x < 0.0 { 0 } else if x == 0.0 { 1 } else { 2 };
+		features.push((start + sign(rows), 1.0));
+		features.push((start + SIGNS + sign(cols), 1.0));
+		let (row, col) = view.position;
+		let tiles = self.positions.tiles(&[row as f64, col as f64]);
+		features.extend(tiles.into_iter().map(|tile| (start + AXES * SIGNS + tile, self.position_weight)));
+		features
+	}
+}
+
+/// The taxi game where the passenger always shows up at one of a few layouts,
+/// so an agent can be trained on some of them and tested on the others
+pub struct LayoutGame {
+	pub state: Game,
+	layouts: Vec<Layout>,
+	reward: PotentialShaping<ClassicTaxi>,
+	rng: XorShiftRng,
+}
+
+impl LayoutGame {
+	/// Every new game picks one of the layouts at random, the shaping of the rewards needs to know the discount factor
+	pub fn new(layouts: Vec<Layout>, discount: f64, seed: u32) -> LayoutGame {
+		assert!(!layouts.is_empty(), "There has to be at least one layout to play");
+		let mut game = LayoutGame {
+			state: Game::new(false),
+			layouts,
+			reward: PotentialShaping::new(ClassicTaxi, discount),
+			rng: seeded_rng(seed),
+		};
+		game.reset();
+		game
+	}
+
+	/// Starts a game with the given layout
+	pub fn start(&mut self, layout: Layout) -> View {
+		let config = GameConfig { seed: Some(self.rng.gen()), layout: Some(layout), ..GameConfig::default() };
+		self.state = Game::with_config(config, false);
+		View::of(&self.state)
+	}
+
+	/// Plays a game with the given layout without exploring, returns the number of moves it took to win or
+	/// `None` if the agent didn't manage to win within `max_moves` moves
+	pub fn play_greedy<G: Agent<View, Dir>>(&mut self, agent: &G, layout: Layout, max_moves: u32) -> Option<u32> {
+		let mut view = self.start(layout);
+		for moves in 1..max_moves + 1 {
+			let step = self.step(agent.best_action(&view, &ACTIONS));
+			if step.done {
+				return if self.state.has_won() { Some(moves) } else { None };
+			}
+			view = step.state;
+		}
+		None
+	}
+}
+
+impl Environment for LayoutGame {
+	type State = View;
+	type Action = Dir;
+
+	fn actions(&self) -> Vec<Dir> {
+		ACTIONS.to_vec()
+	}
+	fn reset(&mut self) -> View {
+		let layout = *self.rng.choose(&self.layouts).unwrap();
+		self.start(layout)
+	}
+	fn step(&mut self, action: Dir) -> Step<View> {
+		let reward = self.state.step(action, &self.reward);
+		Step {
+			state: View::of(&self.state),
+			reward,
+			done: self.state.is_over(),
+		}
+	}
+	fn render(&self) {
+		self.state.print_map()
+	}
+	fn won(&self) -> bool {
+		self.state.has_won()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn active_counts_the_features() {
+		let features = TaxiFeatures::new();
+		let view = View { position: (5, 5), passenger: (3, 4), goal: (8, 8), picked_up: false };
+		for &action in &ACTIONS {
+			let active = features.features(&view, action);
+			assert_eq!(active.len(), features.active());
+			assert!(active.iter().all(|&(i, _)| i < features.size()));
+		}
+	}
+
+	#[test]
+	fn actions_have_their_own_weights() {
+		let features = TaxiFeatures::new();
+		let view = View { position: (5, 5), passenger: (3, 4), goal: (8, 8), picked_up: false };
+		let up = features.features(&view, Dir::Up);
+		let down = features.features(&view, Dir::Down);
+		assert_eq!(up[..SHARED], down[..SHARED]);
+		assert!(up[SHARED..].iter().all(|a| down[SHARED..].iter().all(|b| a.0 != b.0)));
+	}
+}
@@ -0,0 +1,110 @@
+//! The taxi game wrapped up as a learning environment, shared by the reference agent and its examples.
+
+extern crate rand;
+extern crate rl;
+extern crate taxi;
+extern crate termion;
+
+pub mod features;
+pub mod playback;
+pub mod table;
+
+use rand::{Rng, XorShiftRng};
+
+use rl::{seeded_rng, Agent, Environment, Step};
+use taxi::*;
+use taxi::policy::PolicyMap;
+use taxi::reward::{ClassicTaxi, PotentialShaping};
+
+/// Number of cells on the map, positions are numbered row by row
+pub const CELLS: u32 = 11 * 11;
+/// Number of states the agent can tell apart, see `GameState::q_state`
+pub const STATES: u32 = CELLS * 2 * CELLS * CELLS;
+/// The agent has 4 actions: move {up, down, left, right}
+pub const ACTIONS: [Dir; 4] = [Dir::Up, Dir::Right, Dir::Down, Dir::Left];
+
+/// The state as the agent sees it: the position of the taxi, whether the passenger has been picked up and
+/// the positions of the passenger and the goal, all packed into a single number
+pub fn q_state(game: &Game) -> u32 {
+	let index = |(r, c): (u32, u32)| r * 11 + c;
+	let picked_up = if game.passenger_picked_up() { 1 } else { 0 };
+	((index(game.player_position()) * 2 + picked_up) * CELLS +
+	 index(game.passenger_position())) * CELLS + index(game.goal_position())
+}
+
+/// We need to wrap the game since we can't create an `impl Environment` for something from a different crate
+/// But it also adds some convenience because we can add an `impl GameState` to add some custom logic
+pub struct GameState {
+	pub state: Game,
+	/// Rewards are the classic taxi rewards with some shaping to point the agent in the right direction
+	reward: PotentialShaping<ClassicTaxi>,
+	/// Every new game gets its own seed from here, so a run can be repeated exactly
+	seeds: XorShiftRng,
+}
+
+impl GameState {
+	/// The shaping of the rewards needs to know the discount factor the agent learns with
+	pub fn new(discount: f64, seed: u32) -> GameState {
+		let mut seeds = seeded_rng(seed);
+		GameState {
+			state: Game::with_config(GameConfig { seed: Some(seeds.gen()), ..GameConfig::default() }, false),
+			reward: PotentialShaping::new(ClassicTaxi, discount),
+			seeds,
+		}
+	}
+
+	/// The state of the current game as the agent sees it, see `q_state`
+	pub fn q_state(&self) -> u32 {
+		q_state(&self.state)
+	}
+
+	/// What the agent would do in the current game with the taxi on every cell of the map
+	pub fn policy_map<G: Agent<u32, Dir>>(&self, agent: &G) -> PolicyMap {
+		PolicyMap::new(&self.state, |game| {
+			let state = q_state(game);
+			(agent.best_action(&state, &ACTIONS), agent.best_value(&state, &ACTIONS))
+		})
+	}
+
+	/// Plays a game without exploring, returns the number of moves it took to win or `None` if the agent
+	/// didn't manage to win within `max_moves` moves
+	pub fn play_greedy<G: Agent<u32, Dir>>(&mut self, agent: &G, max_moves: u32) -> Option<u32> {
+		let mut state = self.reset();
+		for moves in 1..max_moves + 1 {
+			let step = self.step(agent.best_action(&state, &ACTIONS));
+			if step.done {
+				return if self.state.has_won() { Some(moves) } else { None };
+			}
+			state = step.state;
+		}
+		None
+	}
+}
+
+impl Environment for GameState {
+	type State = u32;
+	type Action = Dir;
+
+	fn actions(&self) -> Vec<Dir> {
+		ACTIONS.to_vec()
+	}
+	fn reset(&mut self) -> u32 {
+		let config = GameConfig { seed: Some(self.seeds.gen()), ..GameConfig::default() };
+		self.state = Game::with_config(config, false);
+		self.q_state()
+	}
+	fn step(&mut self, action: Dir) -> Step<u32> {
+		let reward = self.state.step(action, &self.reward);
+		Step {
+			state: self.q_state(),
+			reward,
+			done: self.state.is_over(),
+		}
+	}
+	fn render(&self) {
+		self.state.print_map()
+	}
+	fn won(&self) -> bool {
+		self.state.has_won()
+	}
+}
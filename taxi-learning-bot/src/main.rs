@@ -1,97 +1,215 @@
-extern crate renforce as re;
+extern crate rl;
 extern crate taxi;
+extern crate taxi_learning_bot as bot;
+extern crate termion;
 
-use taxi::*;
+use std::env;
+use std::io::stdout;
+use std::process;
+use std::time::Duration;
 
-use re::environment::{Environment, Observation};
-use re::environment::Finite;
+use termion::raw::IntoRawMode;
 
-use re::trainer::OnlineTrainer;
-use re::trainer::QLearner;
+use rl::metrics::{Format, MetricsLog};
+use rl::{epsilon_greedy, seeded_rng, Environment, Epsilon, Period, QLearning, Trainer};
 
-use re::agent::Agent;
-use re::agent::qagents::EGreedyQAgent;
+use taxi::render::{NoRender, PlainText, Renderer, Terminal};
 
-use re::util::TimePeriod;
-use re::util::table::QTable;
-use re::util::chooser::Uniform;
+use bot::playback::{self, Playback};
+use bot::{table, GameState, ACTIONS, STATES};
 
-/// We need to wrap the game since we can't create an `impl Environment` for something from a different crate
-/// But it also adds some convenience because we can add an `impl GameState` to add some custom logic
-struct GameState {
-	state: Game
+/// How the games after training are shown
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Render {
+	Terminal,
+	Text,
+	None,
 }
-impl GameState {
-	pub fn new() -> GameState {
-		GameState {
-			state: Game::new(false)
-		}
-	}
 
-	pub fn reward(&self) -> f64 {
-		unimplemented!()
-	}
+/// Parameters for training, they can all be set from the command line
+struct Options {
+	/// How often the agent acts randomly during training
+	epsilon: f64,
+	learning_rate: f64,
+	discount: f64,
+	/// How long to train for
+	period: Period,
+	/// Seed for the games and all random choices of the agent
+	seed: u32,
+	/// Where to save the Q-table after training
+	save: String,
+	/// Q-table to play with instead of training a new one
+	load: Option<String>,
+	/// Where to write the metrics of every training episode, as CSV or JSON lines depending on the extension
+	metrics: Option<String>,
+	/// Print averages of the last this many episodes every this many episodes during training
+	summary: u64,
+	/// Show what the agent would do on every cell and save it as an SVG image here instead of playing
+	policy: Option<String>,
+	render: Render,
+	/// Time between moves when showing the games, in milliseconds
+	delay: u64,
+}
 
-	pub fn q_state(&self) -> ?? {
-		unimplemented!()
+impl Options {
+	/// Reads the options from the command line, anything that isn't given keeps its default
+	fn from_args() -> Options {
+		let mut options = Options {
+			epsilon: 0.75,
+			learning_rate: 0.9,
+			discount: 0.9,
+			period: Period::Timesteps(1_000_000),
+			seed: 0,
+			save: "taxi-q-table.bin".to_string(),
+			load: None,
+			metrics: None,
+			summary: 1000,
+			policy: None,
+			render: Render::Terminal,
+			delay: 100,
+		};
+		let mut args = env::args().skip(1);
+		while let Some(flag) = args.next() {
+			if flag == "--help" || flag == "-h" {
+				usage();
+			}
+			let value = args.next().unwrap_or_else(|| usage());
+			match flag.as_str() {
+				"--epsilon" => options.epsilon = value.parse().unwrap_or_else(|_| usage()),
+				"--learning-rate" => options.learning_rate = value.parse().unwrap_or_else(|_| usage()),
+				"--discount" => options.discount = value.parse().unwrap_or_else(|_| usage()),
+				"--timesteps" => options.period = Period::Timesteps(value.parse().unwrap_or_else(|_| usage())),
+				"--episodes" => options.period = Period::Episodes(value.parse().unwrap_or_else(|_| usage())),
+				"--seed" => options.seed = value.parse().unwrap_or_else(|_| usage()),
+				"--save" => options.save = value,
+				"--load" => options.load = Some(value),
+				"--metrics" => options.metrics = Some(value),
+				"--summary" => options.summary = value.parse().unwrap_or_else(|_| usage()),
+				"--policy" => options.policy = Some(value),
+				"--render" => options.render = match value.as_str() {
+					"terminal" => Render::Terminal,
+					"text" => Render::Text,
+					"none" => Render::None,
+					_ => usage(),
+				},
+				"--delay" => options.delay = value.parse().unwrap_or_else(|_| usage()),
+				_ => usage(),
+			}
+		}
+		options
 	}
 }
 
-impl Environment for GameState {
-	type State = ??;
-	type Action = Finite;
-
-	fn state_space(&self) -> Self::State {
-		unimplemented!()
-	}
-	fn action_space(&self) -> Finite {
-		// The agent has 4 actions: move {up, down, left, right}
-		Finite::new(4)
-	}
-	fn step(&mut self, action: &u32) -> Observation<Self::State> {
-		unimplemented!()
-	}
-	fn reset(&mut self) -> Observation<Self::State> {
-		unimplemented!()
-	}
-	fn render(&self) {
-		unimplemented!()
-	}
+/// Prints how to use the bot and exits
+fn usage() -> ! {
+	println!("Usage: taxi-learning-bot [--epsilon E] [--learning-rate A] [--discount G] \
+			  [--timesteps N | --episodes N] [--seed S] [--save FILE | --load FILE] \
+			  [--metrics FILE] [--summary N] [--policy FILE] [--render terminal|text|none] [--delay MS]");
+	println!();
+	println!("  --epsilon        how often to act randomly during training (default 0.75)");
+	println!("  --learning-rate  how much every update changes the Q-table (default 0.9)");
+	println!("  --discount       how much future rewards count (default 0.9)");
+	println!("  --timesteps      train for this many moves (default 1000000)");
+	println!("  --episodes       train for this many games instead");
+	println!("  --seed           seed for the games and the agent (default 0)");
+	println!("  --save           where to save the Q-table after training (default taxi-q-table.bin)");
+	println!("  --load           skip training and play with a previously saved Q-table");
+	println!("  --metrics        write the metrics of every training episode to a .csv or .jsonl file");
+	println!("  --summary        print averages of the last N episodes every N episodes, 0 for none (default 1000)");
+	println!("  --policy         show the agent's moves and values on every cell and save them as an SVG image,");
+	println!("                   instead of playing");
+	println!("  --render         how to show the games after training: in colour on the terminal, as plain text");
+	println!("                   or not at all (default terminal)");
+	println!("  --delay          milliseconds between moves when showing the games (default 100)");
+	println!();
+	println!("While the games are shown: {}", playback::HELP);
+	process::exit(1)
 }
 
 /// You're highly encouraged to play with the parameters here, but I will provide some standard basic ones
 /// Changing the randomless during learning and the iterations will have the biggest effect,
 /// but changing learning and discount rates will also have a more subtle impact
 fn main() {
-	let mut env = GameState::new();
-	// The agent will use a table as its Q-function
-	let q_func = QTable::new();
-	// Creates an epsilon greedy Q-agent
-	// Agent will act randomly 75% of the time in a Uniform distribution during training, more or less being "random exploration"
-	let mut agent = EGreedyQAgent::new(q_func, env.action_space(), 0.75, Uniform);
+	let options = Options::from_args();
+	let mut env = GameState::new(options.discount, options.seed);
 	// We will use Q-learning to train the agent with
-	// discount factor and learning rate both 0.9 and
-	// 1 000 000 training iterations
-	let mut trainer = QLearner::new(env.action_space(), 0.9, 0.9, TimePeriod::TIMESTEPS(1_000_000));
+	// discount factor and learning rate both 0.9 by default
+	let mut agent = QLearning::new(options.learning_rate, options.discount);
+	match options.load {
+		Some(ref path) => {
+			agent.table = table::load(path, STATES).unwrap_or_else(|e| {
+				println!("Couldn't load Q-table from {}: {}", path, e);
+				process::exit(1)
+			});
+		}
+		None => {
+			// The agent explores with an epsilon greedy policy,
+			// by default it will act randomly 75% of the time in a Uniform distribution during training,
+			// more or less being "random exploration", for 1 000 000 training iterations
+			let mut trainer = Trainer::new(Epsilon::Constant(options.epsilon), options.period, options.seed);
+			trainer.summary_every = options.summary;
+			if let Some(ref path) = options.metrics {
+				match MetricsLog::create(path, Format::from_path(path)) {
+					Ok(log) => trainer.log = Some(log),
+					Err(e) => println!("Couldn't create metrics file {}: {}", path, e),
+				}
+			}
+
+			// Magic happens
+			trainer.train(&mut agent, &mut env);
+
+			if let Err(e) = table::save(&agent.table, STATES, &options.save) {
+				println!("Couldn't save Q-table to {}: {}", options.save, e);
+			}
+		}
+	}
 
-	// Magic happens
-	trainer.train(&mut agent, &mut env);
+	if let Some(ref path) = options.policy {
+		env.reset();
+		let policy = env.policy_map(&agent);
+		policy.print();
+		if let Err(e) = policy.save_svg(path) {
+			println!("Couldn't save the policy to {}: {}", path, e);
+		}
+		return;
+	}
 
-	// Change epsilon to act randomly 5% of the time (to avoid the bot getting stuck in a loop)
-	// And run a game on-screen
-	let mut obs = env.reset();
-	agent.set_epsilon(0.05);
-	loop {
-		while !obs.done {
-			env.render();
-			let action = agent.get_action(&obs.state);
-			obs = env.step(&action);
-			std::thread::sleep(std::time::Duration::from_millis(100));
+	// Act randomly 5% of the time (to avoid the bot getting stuck in a loop)
+	// And run games on-screen until the user quits
+	let mut renderer: Box<dyn Renderer> = match options.render {
+		Render::Terminal => Box::new(Terminal { footer: playback::HELP.to_string() }),
+		Render::Text => Box::new(PlainText::new(stdout())),
+		Render::None => {
+			println!("Playing without showing the games, press q and <Enter> to quit");
+			Box::new(NoRender)
+		}
+	};
+	// Raw mode makes single key presses work without <Enter>, but plain text needs normal line endings
+	let raw = if options.render == Render::Terminal { stdout().into_raw_mode().ok() } else { None };
+	let mut playback = Playback::start(Duration::from_millis(options.delay));
+	let mut rng = seeded_rng(options.seed);
+	let mut state = env.reset();
+	'games: loop {
+		let mut done = false;
+		while !done {
+			renderer.render(&env.state);
+			if !playback.wait() {
+				break 'games;
+			}
+			let action = epsilon_greedy(&agent, &state, &ACTIONS, 0.05, &mut rng);
+			let step = env.step(action);
+			state = step.state;
+			done = step.done;
 		}
 		// We're done with the game here, so we could stop, or we could play another game.
 		// Playing another game will be more insteresting since the game has some random state each time
-		env.render();
-		obs = env.reset();
-		std::thread::sleep(std::time::Duration::from_millis(500));
+		renderer.render(&env.state);
+		let pause = playback.delay * 5;
+		if !playback.wait_for(pause) {
+			break;
+		}
+		state = env.reset();
 	}
+	drop(raw);
+	println!();
 }
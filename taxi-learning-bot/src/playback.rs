@@ -0,0 +1,107 @@
+//! Controls for watching the agent play: pause, step through a game move by move, change the speed and quit.
+//!
+//! Keys are read in a background thread so the game keeps going while nobody presses anything.
+
+use std::io::stdin;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use termion::event::Key;
+use termion::input::TermRead;
+
+/// What the keys ask for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+	/// Pause, or continue when paused
+	Pause,
+	/// Make a single move while paused
+	Step,
+	Faster,
+	Slower,
+	Quit,
+}
+
+impl Command {
+	fn of(key: Key) -> Option<Command> {
+		match key {
+			Key::Char(' ') | Key::Char('p') => Some(Command::Pause),
+			Key::Char('n') | Key::Char('s') | Key::Right => Some(Command::Step),
+			Key::Char('+') | Key::Char('=') | Key::Up => Some(Command::Faster),
+			Key::Char('-') | Key::Down => Some(Command::Slower),
+			Key::Char('q') | Key::Esc | Key::Ctrl('c') => Some(Command::Quit),
+			_ => None,
+		}
+	}
+}
+
+/// The keys, shown under the game
+pub const HELP: &str = "<space> pause | n step | +/- speed | q quit";
+
+/// The fastest and slowest the games can be played
+const MIN_DELAY: Duration = Duration::from_millis(10);
+const MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Keeps track of the speed and whether the game is paused
+pub struct Playback {
+	commands: Receiver<Command>,
+	pub paused: bool,
+	/// Time between moves
+	pub delay: Duration,
+}
+
+impl Playback {
+	/// Starts reading keys from stdin. For single key presses the terminal has to be in raw mode,
+	/// otherwise every key needs an <Enter> after it.
+	pub fn start(delay: Duration) -> Playback {
+		let (sender, commands) = mpsc::channel();
+		thread::spawn(move || {
+			for key in stdin().keys() {
+				let command = match key {
+					Ok(key) => Command::of(key),
+					Err(_) => break,
+				};
+				if let Some(command) = command {
+					if sender.send(command).is_err() {
+						break;
+					}
+				}
+			}
+		});
+		Playback { commands, paused: false, delay }
+	}
+
+	/// Waits until the next move should be made, `false` if the user wants to quit.
+	/// Pausing and changing the speed happen while waiting. When stdin is closed the games just go on.
+	pub fn wait(&mut self) -> bool {
+		self.wait_for(self.delay)
+	}
+
+	/// Like `wait` but for a different amount of time, for example to show the end of a game a bit longer
+	pub fn wait_for(&mut self, delay: Duration) -> bool {
+		let deadline = Instant::now() + delay;
+		loop {
+			let command = if self.paused {
+				self.commands.recv().map_err(|_| RecvTimeoutError::Disconnected)
+			} else {
+				self.commands.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+			};
+			match command {
+				Ok(Command::Pause) => self.paused = !self.paused,
+				Ok(Command::Step) => if self.paused {
+					return true;
+				},
+				Ok(Command::Faster) => self.delay = (self.delay / 2).max(MIN_DELAY),
+				Ok(Command::Slower) => self.delay = (self.delay * 2).min(MAX_DELAY),
+				Ok(Command::Quit) => return false,
+				Err(RecvTimeoutError::Timeout) => return true,
+				// Nothing can unpause anymore, so keep playing
+				Err(RecvTimeoutError::Disconnected) => {
+					self.paused = false;
+					thread::sleep(deadline.saturating_duration_since(Instant::now()));
+					return true;
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,155 @@
+//! Saving and loading Q-tables, so a trained agent doesn't have to be thrown away.
+//!
+//! The file format is a small binary one, all numbers are little endian:
+//! the magic bytes `TAXIQ`, a `u32` format version, the `u32` number of states and actions the table was
+//! trained for, a `u64` count of entries and then that many entries of `u32` state, `u32` action and `f64` value.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use rl::QTable;
+use taxi::Dir;
+
+use ACTIONS;
+
+const MAGIC: &[u8; 5] = b"TAXIQ";
+const VERSION: u32 = 1;
+
+/// Writes a table for `states` states and all four directions to a file
+pub fn save<P: AsRef<Path>>(table: &QTable<u32, Dir>, states: u32, path: P) -> io::Result<()> {
+	let mut out = BufWriter::new(File::create(path)?);
+	write(table, states, &mut out)?;
+	out.flush()
+}
+
+/// Reads a table from a file, making sure it was made for `states` states and all four directions
+pub fn load<P: AsRef<Path>>(path: P, states: u32) -> io::Result<QTable<u32, Dir>> {
+	read(&mut BufReader::new(File::open(path)?), states)
+}
+
+fn write<W: Write>(table: &QTable<u32, Dir>, states: u32, out: &mut W) -> io::Result<()> {
+	out.write_all(MAGIC)?;
+	out.write_all(&VERSION.to_le_bytes())?;
+	out.write_all(&states.to_le_bytes())?;
+	out.write_all(&(ACTIONS.len() as u32).to_le_bytes())?;
+	out.write_all(&(table.len() as u64).to_le_bytes())?;
+	for (&(state, action), value) in table.iter() {
+		out.write_all(&state.to_le_bytes())?;
+		out.write_all(&(action as u32).to_le_bytes())?;
+		out.write_all(&value.to_bits().to_le_bytes())?;
+	}
+	Ok(())
+}
+
+fn read<R: Read>(input: &mut R, states: u32) -> io::Result<QTable<u32, Dir>> {
+	let mut magic = [0; 5];
+	input.read_exact(&mut magic)?;
+	if &magic != MAGIC {
+		return Err(invalid("not a saved Q-table"));
+	}
+	if read_u32(input)? != VERSION {
+		return Err(invalid("unsupported Q-table version"));
+	}
+	if read_u32(input)? != states || read_u32(input)? != ACTIONS.len() as u32 {
+		return Err(invalid("Q-table was trained for a different state or action space"));
+	}
+	let mut table = QTable::new();
+	for _ in 0..read_u64(input)? {
+		let state = read_u32(input)?;
+		let action = Dir::from_u32(read_u32(input)?).map_err(invalid)?;
+		let value = f64::from_bits(read_u64(input)?);
+		if state >= states {
+			return Err(invalid("Q-table has an entry outside of its state space"));
+		}
+		table.set(&state, action, value);
+	}
+	Ok(table)
+}
+
+fn invalid(message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u32<R: Read>(input: &mut R) -> io::Result<u32> {
+	let mut bytes = [0; 4];
+	input.read_exact(&mut bytes)?;
+	Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(input: &mut R) -> io::Result<u64> {
+	let mut bytes = [0; 8];
+	input.read_exact(&mut bytes)?;
+	Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Cursor;
+
+	fn table() -> QTable<u32, Dir> {
+		let mut table = QTable::new();
+		table.set(&0, Dir::Up, 1.5);
+		table.set(&3, Dir::Left, -2.25);
+		table.set(&9, Dir::Down, f64::MIN_POSITIVE);
+		table
+	}
+
+	fn bytes(table: &QTable<u32, Dir>, states: u32) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		write(table, states, &mut bytes).unwrap();
+		bytes
+	}
+
+	fn error(bytes: Vec<u8>, states: u32) -> String {
+		read(&mut Cursor::new(bytes), states).unwrap_err().to_string()
+	}
+
+	#[test]
+	fn round_trip() {
+		let table = table();
+		assert_eq!(read(&mut Cursor::new(bytes(&table, 10)), 10).unwrap(), table);
+		assert_eq!(read(&mut Cursor::new(bytes(&QTable::new(), 10)), 10).unwrap(), QTable::new());
+	}
+
+	#[test]
+	fn bad_magic_is_rejected() {
+		let mut bytes = bytes(&table(), 10);
+		bytes[0] = b'X';
+		assert_eq!(error(bytes, 10), "not a saved Q-table");
+	}
+
+	#[test]
+	fn other_version_is_rejected() {
+		let mut bytes = bytes(&table(), 10);
+		bytes[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+		assert_eq!(error(bytes, 10), "unsupported Q-table version");
+	}
+
+	#[test]
+	fn other_state_count_is_rejected() {
+		assert_eq!(error(bytes(&table(), 10), 11), "Q-table was trained for a different state or action space");
+	}
+
+	#[test]
+	fn other_action_count_is_rejected() {
+		let mut bytes = bytes(&table(), 10);
+		bytes[MAGIC.len() + 8..MAGIC.len() + 12].copy_from_slice(&5u32.to_le_bytes());
+		assert_eq!(error(bytes, 10), "Q-table was trained for a different state or action space");
+	}
+
+	#[test]
+	fn entry_outside_the_state_space_is_rejected() {
+		let mut bytes = bytes(&table(), 10);
+		bytes[MAGIC.len() + 4..MAGIC.len() + 8].copy_from_slice(&5u32.to_le_bytes());
+		assert_eq!(error(bytes, 5), "Q-table has an entry outside of its state space");
+	}
+
+	#[test]
+	fn truncated_file_is_rejected() {
+		let mut bytes = bytes(&table(), 10);
+		bytes.pop();
+		assert_eq!(read(&mut Cursor::new(bytes), 10).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+	}
+}
@@ -0,0 +1,60 @@
+//! Checks that every tabular agent learns to play the taxi game.
+//! Each one is trained with the same small budget and then has to win a set of games it hasn't seen
+//! without exploring, in close to the fewest possible moves.
+
+extern crate rl;
+extern crate taxi;
+extern crate taxi_learning_bot as bot;
+
+use rl::*;
+use taxi::Dir;
+
+use bot::GameState;
+
+const DISCOUNT: f64 = 0.95;
+const EPISODES: u64 = 3_000;
+const GAMES: u32 = 20;
+/// The longest game needs 21 moves when played perfectly
+const MAX_MOVES: u32 = 25;
+
+/// Trains an agent and checks that it wins every evaluation game
+fn check<G: Agent<u32, Dir>>(mut agent: G) {
+	let mut env = GameState::new(DISCOUNT, 1);
+	let epsilon = Epsilon::Linear { start: 1.0, end: 0.05, steps: EPISODES * 20 };
+	let mut trainer = Trainer::new(epsilon, Period::Episodes(EPISODES), 1);
+	trainer.train(&mut agent, &mut env);
+
+	let mut evaluation = GameState::new(DISCOUNT, 2);
+	let moves = (0..GAMES).filter_map(|_| evaluation.play_greedy(&agent, MAX_MOVES)).collect::<Vec<_>>();
+	assert_eq!(moves.len() as u32, GAMES, "won {}/{} games after {} timesteps", moves.len(), GAMES, trainer.timesteps());
+}
+
+#[test]
+fn q_learning() {
+	check(QLearning::new(0.5, DISCOUNT));
+}
+
+#[test]
+fn sarsa() {
+	check(Sarsa::new(0.5, DISCOUNT));
+}
+
+#[test]
+fn expected_sarsa() {
+	check(ExpectedSarsa::new(0.5, DISCOUNT));
+}
+
+#[test]
+fn double_q_learning() {
+	check(DoubleQLearning::new(0.5, DISCOUNT));
+}
+
+#[test]
+fn n_step_sarsa() {
+	check(NStepSarsa::new(4, 0.5, DISCOUNT));
+}
+
+#[test]
+fn monte_carlo() {
+	check(MonteCarlo::new(0.1, DISCOUNT));
+}
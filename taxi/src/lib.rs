@@ -1,16 +1,25 @@
 extern crate ansi_term;
+extern crate game_rng;
 extern crate rand;
+extern crate termion;
+
+pub mod policy;
+pub mod render;
+pub mod reward;
 
 use rand::Rng;
+use std::collections::VecDeque;
 use std::fmt;
-use std::io::Write;
+use std::hash::{Hash, Hasher};
 
-use ansi_term::Colour::{White, Black, Yellow, Green, Cyan};
+use ansi_term::Colour;
+use ansi_term::Colour::{White, Black, Yellow, Green, Purple, Blue, Red};
+use game_rng::GameRng;
 
-/// Helper function to clear the terminal screen, not tested on Windows
-fn clear_screen() {
-	std::io::stdout().write_all("\x1b[2J\x1b[1;1H".as_bytes()).unwrap()
-}
+pub use render::Renderer;
+pub use reward::RewardFunction;
+
+static DELIVERY_VALUE: i32 = 20;
 
 /// Move direction
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
@@ -32,15 +41,131 @@ impl Dir {
 			_ => Err("Cannot convert u32 to `Dir`"),
 		}
 	}
+
+	/// The two directions at a right angle to this one
+	pub fn perpendicular(&self) -> (Dir, Dir) {
+		match *self {
+			Dir::Up | Dir::Down => (Dir::Left, Dir::Right),
+			Dir::Right | Dir::Left => (Dir::Up, Dir::Down),
+		}
+	}
+}
+
+/// Settings for a game, the default is the classic fully deterministic taxi
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameConfig {
+	/// Probability that a move slips, the taxi then moves in a perpendicular direction or stays put instead
+	pub slip: f64,
+	/// Seed for the game RNG, `None` seeds it randomly
+	pub seed: Option<u32>,
+	/// How many passengers there are and how they show up
+	pub rides: RideConfig,
+	/// Pickup and destination of the first passenger, `None` picks them at random from
+	/// `PASSENGER_SPOTS` and `GOAL_SPOTS` like for everyone else. Both have to be on the road, not on a wall
+	pub layout: Option<((u32, u32), (u32, u32))>,
+	/// Size of the fuel tank, every move costs one unit of fuel. `None` means the taxi never runs out
+	/// and there are no fuel stations on the map
+	pub fuel: Option<u32>,
+	/// Number of taxis driving around the same map, at most 4
+	pub taxis: usize,
+}
+
+impl Default for GameConfig {
+	fn default() -> GameConfig {
+		GameConfig {
+			slip: 0.0,
+			seed: None,
+			rides: RideConfig::default(),
+			layout: None,
+			fuel: None,
+			taxis: 1,
+		}
+	}
+}
+
+impl Eq for GameConfig {}
+
+impl Hash for GameConfig {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.slip.to_bits().hash(state);
+		self.seed.hash(state);
+		self.rides.hash(state);
+		self.layout.hash(state);
+		self.fuel.hash(state);
+		self.taxis.hash(state);
+	}
+}
+
+/// Settings for the passengers, the default is the single passenger of the classic taxi
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct RideConfig {
+	/// Total number of passengers that show up during the game
+	pub passengers: u32,
+	/// How many passengers fit in a taxi at the same time
+	pub capacity: usize,
+	/// A new passenger shows up every `spawn_interval` moves, 0 means they're all there from the start
+	pub spawn_interval: u32,
+}
+
+impl Default for RideConfig {
+	fn default() -> RideConfig {
+		RideConfig {
+			passengers: 1,
+			capacity: 1,
+			spawn_interval: 0,
+		}
+	}
+}
+
+/// Where a passenger is on their journey
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum RideState {
+	Waiting,
+	Riding,
+	Delivered,
+}
+
+/// A passenger waiting to be picked up and driven to their destination
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct Ride {
+	/// Where the passenger waits, defined as (row, column) coordinate on the world map
+	pub pickup: (u32, u32),
+	/// Where the passenger wants to go, defined as (row, column) coordinate on the world map
+	pub destination: (u32, u32),
+	/// The move on which the passenger showed up
+	pub spawned_at: u32,
+	/// Whether the passenger is waiting, in the taxi or delivered
+	pub state: RideState,
+	/// Index of the taxi that picked the passenger up
+	pub taxi: Option<usize>,
+}
+
+/// What a single taxi gets to see, along with the reward it got for its last action
+#[derive(PartialEq, Clone, Debug)]
+pub struct Observation {
+	/// Position of this taxi, defined as (row, column) coordinate on the world map
+	pub position: (u32, u32),
+	/// Positions of the other taxis, in the order of their indexes
+	pub others: Vec<(u32, u32)>,
+	/// Passengers waiting to be picked up, as (pickup, destination) pairs
+	pub waiting: Vec<((u32, u32), (u32, u32))>,
+	/// Destinations of the passengers riding in this taxi
+	pub riding: Vec<(u32, u32)>,
+	/// Fuel left in the tank of this taxi, `None` if the game is played without fuel
+	pub fuel: Option<u32>,
+	/// Reward for the last action of this taxi
+	pub reward: f64,
+	/// True if this taxi can't do anything anymore, either because the game is over or it ran out of fuel
+	pub done: bool,
 }
 
 /// Object representing things on the map.
-/// You need to pick up a `Passenger` and deliver it to `Goal`, `Wall`s are potentially in the way.
+/// You need to pick up passengers and deliver them to their destinations, `Wall`s are potentially in the way.
+/// Driving onto a `FuelStation` fills up the tank.
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
 enum Object {
 	Wall,
-	Passenger,
-	Goal,
+	FuelStation,
 	Empty,
 }
 
@@ -48,13 +173,15 @@ impl fmt::Display for Object {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match *self {
 			Object::Wall => write!(f, "{}", White.paint("\u{2588}")),
-			Object::Passenger => write!(f, "{}", Yellow.paint("\u{2588}")),
-			Object::Goal => write!(f, "{}", Green.paint("\u{2588}")),
+			Object::FuelStation => write!(f, "{}", Colour::Fixed(208).paint("\u{2588}")),
 			Object::Empty => write!(f, "{}", Black.paint("\u{2588}")),
 		}
 	}
 }
 
+/// Colors used to tell passengers apart on the map when there's more than one
+static RIDE_COLOURS: [Colour; 5] = [Yellow, Green, Purple, Blue, Red];
+
 /// Calculates distance between two points
 pub fn distance(p0: (u32, u32), p1: (u32, u32)) -> f64 {
 	((((p0.0 as i64 - p1.0 as i64).pow(2) + (p0.1 as i64 - p1.1 as i64).pow(2)) as f64).sqrt())
@@ -63,84 +190,209 @@ pub fn distance(p0: (u32, u32), p1: (u32, u32)) -> f64 {
 /// Type alias for the game-world
 type World = [[Object; Game::WORLD_WIDTH]; Game::WORLD_HEIGHT];
 
+/// A taxi driving around the map
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+struct Taxi {
+	/// Position of the taxi, defined as (row, column) coordinate on the world map
+	position: (u32, u32),
+	/// Fuel left in the tank, `None` if the game is played without fuel
+	fuel: Option<u32>,
+	/// How many passengers this taxi has delivered
+	delivered: u32,
+}
+
 /// The Game with accompanying state
 #[derive(PartialEq, Eq, Hash, Clone)]
 pub struct Game {
 	/// The current state of the game board, will change when you take actions
 	world: World,
-	/// Position of the player, defined as (row, column) coordinate on the world map
-	position: (u32, u32),
-	/// Position of the passenger, defined as (row, column) coordinate on the world map
-	passenger: (u32, u32),
-	/// Passenger has been picked up
-	picked_up: bool,
-	/// Position of the goal, defined as (row, column) coordinate on the world map
-	goal: (u32, u32),
-	/// How many moves the player has made
+	/// The taxis on the map, the first one is the player in a single player game
+	taxis: Vec<Taxi>,
+	/// Every passenger that has shown up so far, in order of appearance
+	rides: Vec<Ride>,
+	/// Total number of moves passengers have spent waiting for delivery
+	waiting: u32,
+	/// How many moves the player has made, all taxis move at the same time so this is shared
 	moves: u32,
+	/// The settings this game was created with
+	config: GameConfig,
+	/// Source of all randomness in the game, seeded from the config
+	rng: GameRng,
 }
 
 impl Game {
 	const WORLD_WIDTH: usize = 11;
 	const WORLD_HEIGHT: usize = 11;
+	/// Number of planes in `grid`
+	pub const GRID_PLANES: usize = 8;
 
 	/// Initialize a new game state
 	pub fn new(print: bool) -> Game {
-		let (w, p, g) = simple_world();
+		Game::with_config(GameConfig::default(), print)
+	}
+
+	/// Initialize a new game state with the given settings
+	pub fn with_config(config: GameConfig, print: bool) -> Game {
+		assert!(config.rides.passengers > 0, "A game needs at least one passenger");
+		assert!(config.rides.capacity > 0, "The taxi needs room for at least one passenger");
+		assert!(config.taxis > 0 && config.taxis <= TAXI_SPOTS.len(), "A game has between 1 and 4 taxis");
+		let rng = GameRng::new(config.seed);
+		let mut world = simple_world();
+		if let Some((pickup, destination)) = config.layout {
+			for &(r, c) in &[pickup, destination] {
+				let open = match world.get(r as usize).and_then(|row| row.get(c as usize)) {
+					Some(&object) => object != Object::Wall,
+					None => false,
+				};
+				assert!(open, "The layout puts a passenger or destination at {:?}, which isn't on the road", (r, c));
+			}
+		}
+		if config.fuel.is_some() {
+			for &(r, c) in FUEL_SPOTS.iter() {
+				world[r as usize][c as usize] = Object::FuelStation;
+			}
+		}
+		let taxis = TAXI_SPOTS[..config.taxis].iter().map(|&position| {
+			Taxi {
+				position,
+				fuel: config.fuel,
+				delivered: 0,
+			}
+		}).collect();
 		let mut game = Game {
-			world: w,
-			position: (1, 1),
-			passenger: p,
-			picked_up: false,
-			goal: g,
+			world,
+			taxis,
+			rides: Vec::new(),
+			waiting: 0,
 			moves: 0,
+			config,
+			rng,
 		};
-		game.world[p.0 as usize][p.1 as usize] = Object::Passenger;
-		game.world[g.0 as usize][g.1 as usize] = Object::Goal;
+		game.spawn_rides();
 		if print {
 			game.print_map()
 		};
 		game
 	}
 
+	/// The settings this game was created with
+	pub fn config(&self) -> &GameConfig {
+		&self.config
+	}
+
 	/// Returns size of the game world
 	pub fn world_size(&self) -> (usize, usize) {
 		(Self::WORLD_HEIGHT, Self::WORLD_WIDTH)
 	}
 
-	/// Returns true if player has won the game
+	/// Returns true if player has won the game, meaning every passenger has been delivered
 	pub fn has_won(&self) -> bool {
-		self.position == self.goal && self.picked_up
+		self.rides.len() as u32 == self.config.rides.passengers &&
+		self.rides.iter().all(|r| r.state == RideState::Delivered)
+	}
+
+	/// Returns true if every taxi ran out of fuel before delivering every passenger
+	pub fn has_lost(&self) -> bool {
+		self.taxis.iter().all(|t| t.fuel == Some(0)) && !self.has_won()
 	}
 
-	/// Passenger position
+	/// Returns true if the game has been either won or lost
+	pub fn is_over(&self) -> bool {
+		self.has_won() || self.has_lost()
+	}
+
+	/// Fuel left in the tank, `None` if the game is played without fuel
+	pub fn fuel(&self) -> Option<u32> {
+		self.taxis[0].fuel
+	}
+
+	/// Passenger position, this is the first passenger if there are several
 	pub fn passenger_position(&self) -> (u32, u32) {
-		self.passenger
+		self.rides[0].pickup
 	}
 
-	/// Goal position
+	/// Goal position, this is the destination of the first passenger if there are several
 	pub fn goal_position(&self) -> (u32, u32) {
-		self.goal
+		self.rides[0].destination
 	}
 
-	/// Returns player position
+	/// Returns player position, this is the first taxi if there are several
 	pub fn player_position(&self) -> (u32, u32) {
-		self.position
+		self.taxis[0].position
 	}
 
-	/// Returns true if passenger has been picked up
+	/// Returns true if passenger has been picked up, this is the first passenger if there are several
 	pub fn passenger_picked_up(&self) -> bool {
-		self.picked_up
+		self.rides[0].state != RideState::Waiting
+	}
+
+	/// Every passenger that has shown up so far, in order of appearance
+	pub fn rides(&self) -> &[Ride] {
+		&self.rides
+	}
+
+	/// How many passengers have been delivered
+	pub fn delivered(&self) -> u32 {
+		self.rides.iter().filter(|r| r.state == RideState::Delivered).count() as u32
+	}
+
+	/// Score of the game, every delivered passenger is worth some points but every move a passenger
+	/// spends waiting for delivery costs one
+	pub fn score(&self) -> i32 {
+		DELIVERY_VALUE * self.delivered() as i32 - self.waiting as i32
+	}
+
+	/// Number of taxis on the map
+	pub fn taxi_count(&self) -> usize {
+		self.taxis.len()
+	}
+
+	/// Position of the taxi with the given index
+	pub fn taxi_position(&self, taxi: usize) -> (u32, u32) {
+		self.taxis[taxi].position
+	}
+
+	/// Fuel left in the tank of the taxi with the given index
+	pub fn taxi_fuel(&self, taxi: usize) -> Option<u32> {
+		self.taxis[taxi].fuel
+	}
+
+	/// How many passengers the taxi with the given index has delivered
+	pub fn delivered_by(&self, taxi: usize) -> u32 {
+		self.taxis[taxi].delivered
+	}
+
+	/// Length of the shortest path between two points driving around the walls,
+	/// `None` if one can't be reached from the other
+	pub fn travel_distance(&self, from: (u32, u32), to: (u32, u32)) -> Option<u32> {
+		let mut distances = [[None; Game::WORLD_WIDTH]; Game::WORLD_HEIGHT];
+		let mut queue = VecDeque::new();
+		distances[from.0 as usize][from.1 as usize] = Some(0);
+		queue.push_back(from);
+		while let Some(cell) = queue.pop_front() {
+			let d = distances[cell.0 as usize][cell.1 as usize].unwrap();
+			if cell == to {
+				return Some(d);
+			}
+			let neighbours = [(cell.0 - 1, cell.1), (cell.0, cell.1 + 1), (cell.0 + 1, cell.1), (cell.0, cell.1 - 1)];
+			for &(r, c) in neighbours.iter() {
+				if self.world[r as usize][c as usize] != Object::Wall && distances[r as usize][c as usize].is_none() {
+					distances[r as usize][c as usize] = Some(d + 1);
+					queue.push_back((r, c));
+				}
+			}
+		}
+		None
 	}
 
 	/// Returns a distance to a game goal
 	pub fn distance_to_goal(&self) -> f64 {
-		distance(self.goal, self.position)
+		distance(self.goal_position(), self.player_position())
 	}
 
 	/// Returns a distance to a passenger
 	pub fn distance_to_passenger(&self) -> f64 {
-		distance(self.passenger, self.position)
+		distance(self.passenger_position(), self.player_position())
 	}
 
 	/// Enter move directly and optionally print the map
@@ -157,53 +409,276 @@ impl Game {
 		}
 	}
 
-	/// Makes a move and mutates the board, doesn't return anything
+	/// Makes a move and returns the reward for it according to the given reward function
+	pub fn step<R: RewardFunction + ?Sized>(&mut self, dir: Dir, reward: &R) -> f64 {
+		let before = self.clone();
+		self.make_move(dir);
+		reward.reward(&before, self, 0)
+	}
+
+	/// Makes a move and mutates the board, doesn't return anything.
+	/// If the game is slippery the taxi might end up going somewhere else than asked.
+	/// Once the taxi is out of fuel it can't move anymore.
+	/// In a game with several taxis this moves the first one and the others stay where they are.
 	pub fn make_move(&mut self, dir: Dir) {
-		self.moves += 1;
-		let target = match dir {
-			Dir::Up => (self.position.0 - 1, self.position.1),
-			Dir::Right => (self.position.0, self.position.1 + 1),
-			Dir::Down => (self.position.0 + 1, self.position.1),
-			Dir::Left => (self.position.0, self.position.1 - 1)
-		};
-		match self.world[target.0 as usize][target.1 as usize] {
-			Object::Wall => (),
-			Object::Goal => self.position = target,
-			Object::Passenger => {
-				self.picked_up = true;
-				self.world[target.0 as usize][target.1 as usize] = Object::Empty;
-				self.position = target;
+		let mut actions = vec![None; self.taxis.len()];
+		actions[0] = Some(dir);
+		self.tick(&actions);
+	}
+
+	/// Moves every taxi at the same time, one action per taxi, and returns what each of them observes
+	/// along with their reward according to the given reward function.
+	/// Taxis can't drive through each other, two taxis going for the same spot or trying to swap places
+	/// both stay where they are. Passengers go to whichever taxi gets to them first.
+	pub fn step_all<R: RewardFunction + ?Sized>(&mut self, actions: &[Dir], reward: &R) -> Vec<Observation> {
+		assert_eq!(actions.len(), self.taxis.len(), "There must be exactly one action per taxi");
+		let before = self.clone();
+		self.tick(&actions.iter().map(|&dir| Some(dir)).collect::<Vec<_>>());
+		(0..self.taxis.len()).map(|i| {
+			let mut observation = self.observe(i);
+			observation.reward = reward.reward(&before, self, i);
+			observation
+		}).collect()
+	}
+
+	/// What the taxi with the given index currently sees, the reward is 0 since it didn't do anything
+	pub fn observe(&self, taxi: usize) -> Observation {
+		Observation {
+			position: self.taxis[taxi].position,
+			others: self.taxis.iter().enumerate().filter(|&(i, _)| i != taxi).map(|(_, t)| t.position).collect(),
+			waiting: self.rides.iter()
+				.filter(|r| r.state == RideState::Waiting)
+				.map(|r| (r.pickup, r.destination))
+				.collect(),
+			riding: self.rides.iter()
+				.filter(|r| r.state == RideState::Riding && r.taxi == Some(taxi))
+				.map(|r| r.destination)
+				.collect(),
+			fuel: self.taxis[taxi].fuel,
+			reward: 0.0,
+			done: self.is_over() || self.taxis[taxi].fuel == Some(0),
+		}
+	}
+
+	/// What the taxi with the given index sees as a stack of planes the size of the map, for agents that learn
+	/// from the whole picture instead of hand-picked features. Plane by plane and row by row, a cell is 1 if it has
+	/// 0. a wall
+	/// 1. a fuel station
+	/// 2. this taxi
+	/// 3. another taxi
+	/// 4. a passenger waiting for pickup
+	/// 5. the destination of a waiting passenger
+	/// 6. the destination of a passenger riding in this taxi
+	///
+	/// and 0 otherwise. Every cell of the last plane holds how full the tank is, or 1 without fuel.
+	pub fn grid(&self, taxi: usize) -> Vec<f32> {
+		let cells = Game::WORLD_WIDTH * Game::WORLD_HEIGHT;
+		let mut grid = vec![0.0; Game::GRID_PLANES * cells];
+		{
+			let mut set = |plane: usize, (r, c): (u32, u32)| {
+				grid[plane * cells + r as usize * Game::WORLD_WIDTH + c as usize] = 1.0;
+			};
+			for (r, row) in self.world.iter().enumerate() {
+				for (c, object) in row.iter().enumerate() {
+					match *object {
+						Object::Wall => set(0, (r as u32, c as u32)),
+						Object::FuelStation => set(1, (r as u32, c as u32)),
+						Object::Empty => {}
+					}
+				}
+			}
+			for (i, t) in self.taxis.iter().enumerate() {
+				set(if i == taxi { 2 } else { 3 }, t.position);
 			}
-			Object::Empty => {
-				self.position = target;
+			for ride in &self.rides {
+				match ride.state {
+					RideState::Waiting => {
+						set(4, ride.pickup);
+						set(5, ride.destination);
+					}
+					RideState::Riding if ride.taxi == Some(taxi) => set(6, ride.destination),
+					_ => {}
+				}
 			}
 		}
+		let tank = match (self.taxis[taxi].fuel, self.config.fuel) {
+			(Some(fuel), Some(size)) if size > 0 => fuel as f32 / size as f32,
+			_ => 1.0,
+		};
+		for cell in &mut grid[7 * cells..] {
+			*cell = tank;
+		}
+		grid
 	}
 
-	/// Print the map on screen
-	pub fn print_map(&self) {
-		clear_screen();
-		for r in 0..Game::WORLD_HEIGHT {
-			for c in 0..Game::WORLD_WIDTH {
-				let pos = (r as u32, c as u32);
-				if pos == self.position {
-					print!("{}", Cyan.paint("\u{2588}"));
-				} else {
-					print!("{}", self.world[r][c]);
+	/// Advances the game by one move, taxis without an action or fuel stay where they are
+	fn tick(&mut self, actions: &[Option<Dir>]) {
+		if self.is_over() {
+			return;
+		}
+		self.moves += 1;
+		let mut targets = Vec::with_capacity(self.taxis.len());
+		for (i, action) in actions.iter().enumerate() {
+			let position = self.taxis[i].position;
+			let dir = match (*action, self.taxis[i].fuel) {
+				(_, Some(0)) | (None, _) => None,
+				(Some(dir), fuel) => {
+					self.taxis[i].fuel = fuel.map(|f| f - 1);
+					self.slip(dir)
+				}
+			};
+			let target = match dir {
+				Some(Dir::Up) => (position.0 - 1, position.1),
+				Some(Dir::Right) => (position.0, position.1 + 1),
+				Some(Dir::Down) => (position.0 + 1, position.1),
+				Some(Dir::Left) => (position.0, position.1 - 1),
+				None => position,
+			};
+			targets.push(match self.world[target.0 as usize][target.1 as usize] {
+				Object::Wall => position,
+				Object::FuelStation | Object::Empty => target,
+			});
+		}
+		self.resolve_collisions(&mut targets);
+		for (i, target) in targets.into_iter().enumerate() {
+			self.taxis[i].position = target;
+			if self.world[target.0 as usize][target.1 as usize] == Object::FuelStation {
+				self.taxis[i].fuel = self.config.fuel;
+			}
+			self.drop_off_and_pick_up(i);
+		}
+		self.waiting += self.rides.iter().filter(|r| r.state != RideState::Delivered).count() as u32;
+		self.spawn_rides();
+	}
+
+	/// Keeps taxis from driving into each other by making the ones that would collide stay where they are.
+	/// Staying put can cause new collisions, so this goes on until nobody has to change their plans anymore.
+	fn resolve_collisions(&self, targets: &mut [(u32, u32)]) {
+		let positions = self.taxis.iter().map(|t| t.position).collect::<Vec<_>>();
+		loop {
+			let planned = targets.to_vec();
+			let mut changed = false;
+			for i in 0..targets.len() {
+				if planned[i] == positions[i] {
+					continue;
+				}
+				let collides = (0..planned.len()).any(|j| {
+					j != i && (planned[j] == planned[i] || (planned[j] == positions[i] && positions[j] == planned[i]))
+				});
+				if collides {
+					targets[i] = positions[i];
+					changed = true;
 				}
 			}
-			print!("\n\r");
+			if !changed {
+				return;
+			}
+		}
+	}
+
+	/// Decides where the taxi actually goes when asked to go in `dir`, `None` means it stays put.
+	/// A slip is equally likely to go either perpendicular direction or nowhere.
+	fn slip(&mut self, dir: Dir) -> Option<Dir> {
+		if self.config.slip <= 0.0 || self.rng.0.next_f64() >= self.config.slip {
+			return Some(dir);
+		}
+		let (left, right) = dir.perpendicular();
+		*self.rng.0.choose(&[Some(left), Some(right), None]).unwrap()
+	}
+
+	/// Lets out the passengers that have arrived in the given taxi,
+	/// then takes in whoever is waiting where it is as long as there's room
+	fn drop_off_and_pick_up(&mut self, taxi: usize) {
+		let position = self.taxis[taxi].position;
+		for ride in self.rides.iter_mut() {
+			if ride.state == RideState::Riding && ride.taxi == Some(taxi) && ride.destination == position {
+				ride.state = RideState::Delivered;
+				self.taxis[taxi].delivered += 1;
+			}
+		}
+		let mut riding = self.rides.iter().filter(|r| r.state == RideState::Riding && r.taxi == Some(taxi)).count();
+		for ride in self.rides.iter_mut() {
+			if riding < self.config.rides.capacity && ride.state == RideState::Waiting && ride.pickup == position {
+				ride.state = RideState::Riding;
+				ride.taxi = Some(taxi);
+				riding += 1;
+			}
+		}
+	}
+
+	/// Adds the passengers that are due to show up by now
+	fn spawn_rides(&mut self) {
+		let rides = self.config.rides;
+		while (self.rides.len() as u32) < rides.passengers &&
+			  self.moves >= self.rides.len() as u32 * rides.spawn_interval {
+			let (pickup, destination) = match self.config.layout {
+				Some(layout) if self.rides.is_empty() => layout,
+				_ => (*self.rng.0.choose(&PASSENGER_SPOTS).unwrap(), *self.rng.0.choose(&GOAL_SPOTS).unwrap()),
+			};
+			self.rides.push(Ride {
+				pickup,
+				destination,
+				spawned_at: self.moves,
+				state: RideState::Waiting,
+				taxi: None,
+			});
+		}
+	}
+
+	/// The colors of a passenger and of their destination on the map
+	fn ride_colours(&self, index: usize) -> (Colour, Colour) {
+		if self.config.rides.passengers == 1 {
+			(Yellow, Green)
+		} else {
+			let colour = RIDE_COLOURS[index % RIDE_COLOURS.len()];
+			(colour, colour)
+		}
+	}
+
+	/// Print the map on screen, see `render::Terminal`
+	pub fn print_map(&self) {
+		render::Terminal::new().render(self)
+	}
+
+	/// The line under the map with the moves, and the score, fuel and deliveries when they matter
+	pub fn status(&self) -> String {
+		let mut status = format!("Moves: {}", self.moves);
+		if self.config.rides.passengers > 1 {
+			let riding = self.rides.iter().filter(|r| r.state == RideState::Riding).count();
+			status += &format!(" | Delivered: {}/{} | Riding: {}/{} | Score: {}",
+							   self.delivered(), self.config.rides.passengers, riding,
+							   self.config.rides.capacity * self.taxis.len(), self.score());
+		}
+		if let Some(tank) = self.config.fuel {
+			let fuel = self.taxis.iter().map(|t| format!("{}/{}", t.fuel.unwrap_or(0), tank)).collect::<Vec<_>>();
+			status += &format!(" | Fuel: {}", fuel.join(", "));
 		}
-		print!("\n\rMoves: {}", self.moves);
-		print!("\n\r\n\rPress <ESC> to exit game.\n\r");
-		std::io::stdout().flush().unwrap();
+		if self.taxis.len() > 1 {
+			let delivered = self.taxis.iter().map(|t| t.delivered.to_string()).collect::<Vec<_>>();
+			status += &format!(" | Delivered per taxi: {}", delivered.join(", "));
+		}
+		if self.has_lost() {
+			status += " | Out of fuel!";
+		}
+		status
 	}
 }
 
-/// The basic world that we're dealing with, giving a World, a position for the goal and a position for the passenger
-fn simple_world() -> (World, (u32, u32), (u32, u32)) {
-	let mut rng = rand::thread_rng();
-	let w = [
+/// Spots where passengers show up.
+/// Difficulty of the state space increases with the variation in goals and passengers
+pub static PASSENGER_SPOTS: [(u32, u32); 4] = [(3,4), (4,8), (6,1), (6,8)];
+/// Spots passengers want to go to
+pub static GOAL_SPOTS: [(u32, u32); 4] = [(8,8), (1,2), (1,8), (8,1)];
+
+/// Spots where the taxis start out, the first taxi is always in the top left corner
+static TAXI_SPOTS: [(u32, u32); 4] = [(1,1), (9,9), (1,9), (9,1)];
+
+/// Spots with a fuel station, only when playing with fuel
+static FUEL_SPOTS: [(u32, u32); 2] = [(1,5), (9,5)];
+
+/// The basic world that we're dealing with, passengers and their destinations are placed on top of it
+fn simple_world() -> World {
+	[
 		[Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall],
 		[Object::Wall, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Wall],
 		[Object::Wall, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Wall],
@@ -215,9 +690,248 @@ fn simple_world() -> (World, (u32, u32), (u32, u32)) {
 		[Object::Wall, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Wall],
 		[Object::Wall, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Empty, Object::Wall],
 		[Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall, Object::Wall],
-	];
-	// Difficulty of the state space increases with the variation in goals and passengers
-	let goals = vec![(8,8), (1,2), (1,8), (8,1)];
-	let passengers = vec![(3,4), (4,8), (6,1), (6,8)];
-	(w, rng.choose(&passengers).unwrap().clone(), rng.choose(&goals).unwrap().clone())
+	]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn with_layout(layout: ((u32, u32), (u32, u32))) -> Game {
+		Game::with_config(GameConfig { seed: Some(1), layout: Some(layout), ..GameConfig::default() }, false)
+	}
+
+	fn slippery(slip: f64, seed: u32) -> Game {
+		Game::with_config(GameConfig { slip, seed: Some(seed), layout: Some(((9, 9), (9, 8))), ..GameConfig::default() }, false)
+	}
+
+	fn path(mut game: Game) -> Vec<(u32, u32)> {
+		let dirs = [Dir::Down, Dir::Right, Dir::Down, Dir::Right, Dir::Up, Dir::Left];
+		dirs.iter().cycle().take(60).map(|&dir| {
+			game.make_move(dir);
+			game.player_position()
+		}).collect()
+	}
+
+	#[test]
+	fn same_seed_same_path() {
+		assert_eq!(path(slippery(0.5, 7)), path(slippery(0.5, 7)));
+		assert_ne!(path(slippery(0.5, 7)), path(slippery(0.5, 8)));
+	}
+
+	#[test]
+	fn no_slip_goes_where_asked() {
+		let mut game = slippery(0.0, 7);
+		game.make_move(Dir::Down);
+		game.make_move(Dir::Right);
+		assert_eq!(game.player_position(), (2, 2));
+	}
+
+	#[test]
+	fn slips_go_sideways_or_nowhere() {
+		let mut game = slippery(1.0, 7);
+		let mut seen = Vec::new();
+		for _ in 0..100 {
+			game.taxis[0].position = (5, 5);
+			game.make_move(Dir::Up);
+			let position = game.player_position();
+			assert!([(5, 4), (5, 6), (5, 5)].contains(&position), "Slipped to {:?}", position);
+			if !seen.contains(&position) {
+				seen.push(position);
+			}
+		}
+		assert_eq!(seen.len(), 3, "Every kind of slip should happen, only saw {:?}", seen);
+	}
+
+	fn with_rides(rides: RideConfig) -> Game {
+		Game::with_config(GameConfig { seed: Some(1), rides, ..GameConfig::default() }, false)
+	}
+
+	#[test]
+	fn passengers_show_up_every_spawn_interval() {
+		let mut game = with_rides(RideConfig { passengers: 3, capacity: 1, spawn_interval: 2 });
+		let mut counts = Vec::new();
+		for _ in 0..6 {
+			counts.push(game.rides().len());
+			game.make_move(Dir::Down);
+		}
+		assert_eq!(counts, vec![1, 1, 2, 2, 3, 3]);
+		assert_eq!(game.rides().iter().map(|r| r.spawned_at).collect::<Vec<_>>(), vec![0, 2, 4]);
+	}
+
+	#[test]
+	fn spawn_interval_0_has_everyone_from_the_start() {
+		let game = with_rides(RideConfig { passengers: 3, capacity: 1, spawn_interval: 0 });
+		assert_eq!(game.rides().len(), 3);
+		assert!(game.rides().iter().all(|r| r.state == RideState::Waiting && r.spawned_at == 0));
+	}
+
+	#[test]
+	fn taxi_only_takes_capacity_passengers() {
+		let mut game = with_rides(RideConfig { passengers: 3, capacity: 2, spawn_interval: 0 });
+		for ride in &mut game.rides {
+			ride.pickup = (1, 2);
+			ride.destination = (1, 4);
+		}
+		game.make_move(Dir::Right);
+		let states = |game: &Game| game.rides().iter().map(|r| r.state).collect::<Vec<_>>();
+		assert_eq!(states(&game), vec![RideState::Riding, RideState::Riding, RideState::Waiting]);
+		assert!(game.rides()[..2].iter().all(|r| r.taxi == Some(0)));
+		game.make_move(Dir::Right);
+		game.make_move(Dir::Right);
+		assert_eq!(states(&game), vec![RideState::Delivered, RideState::Delivered, RideState::Waiting]);
+		assert_eq!(game.delivered(), 2);
+		assert!(!game.has_won());
+	}
+
+	#[test]
+	fn score_counts_deliveries_and_waiting() {
+		let mut game = with_rides(RideConfig { passengers: 2, capacity: 1, spawn_interval: 0 });
+		game.rides[0].pickup = (1, 2);
+		game.rides[0].destination = (1, 3);
+		game.rides[1].pickup = (2, 3);
+		game.rides[1].destination = (3, 3);
+		assert_eq!(game.score(), 0);
+		// The first one is dropped off on the second move, the second one is still riding on the third
+		for &dir in &[Dir::Right, Dir::Right, Dir::Down, Dir::Down] {
+			game.make_move(dir);
+		}
+		assert!(game.has_won());
+		assert_eq!(game.score(), 2 * DELIVERY_VALUE - (2 + 1 + 1));
+	}
+
+	fn with_fuel(fuel: u32) -> Game {
+		Game::with_config(GameConfig { seed: Some(1), layout: Some(((9, 9), (9, 8))), fuel: Some(fuel), ..GameConfig::default() }, false)
+	}
+
+	#[test]
+	fn running_out_of_fuel_loses() {
+		let mut game = with_fuel(3);
+		game.make_move(Dir::Up);
+		assert_eq!((game.player_position(), game.fuel()), ((1, 1), Some(2)), "Bumping into a wall costs fuel too");
+		game.make_move(Dir::Down);
+		assert!(!game.has_lost());
+		game.make_move(Dir::Down);
+		assert_eq!((game.player_position(), game.fuel()), ((3, 1), Some(0)));
+		assert!(game.has_lost() && game.is_over() && !game.has_won());
+		assert!(game.observe(0).done);
+		game.make_move(Dir::Down);
+		assert_eq!(game.player_position(), (3, 1), "An empty taxi can't move");
+	}
+
+	#[test]
+	fn fuel_station_fills_up_the_tank() {
+		let mut game = with_fuel(4);
+		for _ in 0..4 {
+			game.make_move(Dir::Right);
+		}
+		assert_eq!((game.player_position(), game.fuel()), ((1, 5), Some(4)));
+		assert!(!game.has_lost());
+		game.make_move(Dir::Right);
+		assert_eq!(game.fuel(), Some(3));
+	}
+
+	#[test]
+	fn no_fuel_means_no_stations() {
+		let mut game = slippery(0.0, 1);
+		for _ in 0..20 {
+			game.make_move(Dir::Right);
+		}
+		assert_eq!(game.fuel(), None);
+		assert!(!game.has_lost());
+		assert!(game.world.iter().all(|row| !row.contains(&Object::FuelStation)));
+	}
+
+	fn taxis_at(positions: &[(u32, u32)]) -> Game {
+		let config = GameConfig { seed: Some(1), layout: Some(((8, 2), (8, 3))), taxis: positions.len(), ..GameConfig::default() };
+		let mut game = Game::with_config(config, false);
+		for (taxi, &position) in game.taxis.iter_mut().zip(positions) {
+			taxi.position = position;
+		}
+		game
+	}
+
+	fn drive(game: &mut Game, actions: &[Dir]) -> Vec<(u32, u32)> {
+		game.step_all(actions, &reward::ClassicTaxi).iter().map(|o| o.position).collect()
+	}
+
+	#[test]
+	fn head_on_taxis_both_stay() {
+		let mut game = taxis_at(&[(5, 4), (5, 6)]);
+		assert_eq!(drive(&mut game, &[Dir::Right, Dir::Left]), vec![(5, 4), (5, 6)]);
+	}
+
+	#[test]
+	fn taxis_cant_swap_places() {
+		let mut game = taxis_at(&[(5, 4), (5, 5)]);
+		assert_eq!(drive(&mut game, &[Dir::Right, Dir::Left]), vec![(5, 4), (5, 5)]);
+	}
+
+	#[test]
+	fn taxis_can_follow_each_other() {
+		let mut game = taxis_at(&[(5, 3), (5, 4), (5, 5)]);
+		assert_eq!(drive(&mut game, &[Dir::Right, Dir::Right, Dir::Right]), vec![(5, 4), (5, 5), (5, 6)]);
+	}
+
+	#[test]
+	fn blocked_taxi_holds_up_the_chain() {
+		// The last taxi drives into the wall, so the ones behind it can't move up either
+		let mut game = taxis_at(&[(5, 7), (5, 8), (5, 9)]);
+		assert_eq!(drive(&mut game, &[Dir::Right, Dir::Right, Dir::Right]), vec![(5, 7), (5, 8), (5, 9)]);
+		// Two taxis going for the spot the last one leaves both stay, and so does the one behind them
+		let mut game = taxis_at(&[(5, 7), (5, 8), (4, 9), (5, 9)]);
+		let positions = drive(&mut game, &[Dir::Right, Dir::Right, Dir::Down, Dir::Down]);
+		assert_eq!(positions, vec![(5, 7), (5, 8), (4, 9), (6, 9)]);
+	}
+
+	#[test]
+	fn only_the_first_taxi_moves_in_make_move() {
+		let mut game = taxis_at(&[(5, 4), (5, 6)]);
+		game.make_move(Dir::Right);
+		assert_eq!((game.taxi_position(0), game.taxi_position(1)), ((5, 5), (5, 6)));
+	}
+
+	#[test]
+	fn every_taxi_gets_its_own_observation() {
+		let mut game = taxis_at(&[(6, 2), (9, 9)]);
+		game.rides.push(Ride { pickup: (9, 9), destination: (9, 8), spawned_at: 0, state: RideState::Riding, taxi: Some(1) });
+		game.config.rides.passengers = 2;
+		let observations = game.step_all(&[Dir::Down, Dir::Left], &reward::ClassicTaxi);
+		assert_eq!(observations[0].position, (7, 2));
+		assert_eq!(observations[0].others, vec![(9, 8)]);
+		assert_eq!(observations[0].waiting, vec![((8, 2), (8, 3))]);
+		assert!(observations[0].riding.is_empty());
+		assert_eq!(observations[0].reward, -1.0);
+		assert_eq!(observations[1].position, (9, 8));
+		assert_eq!(observations[1].others, vec![(7, 2)]);
+		assert_eq!(observations[1].reward, 19.0);
+		assert_eq!((game.delivered_by(0), game.delivered_by(1)), (0, 1));
+		assert!(observations.iter().all(|o| !o.done));
+
+		let observations = game.step_all(&[Dir::Down, Dir::Up], &reward::ClassicTaxi);
+		assert_eq!(observations[0].riding, vec![(8, 3)]);
+		assert!(observations[1].riding.is_empty());
+		assert!(observations[0].waiting.is_empty());
+		let observations = game.step_all(&[Dir::Right, Dir::Up], &reward::ClassicTaxi);
+		assert!(game.has_won());
+		assert_eq!(observations.iter().map(|o| (o.reward, o.done)).collect::<Vec<_>>(), vec![(19.0, true), (-1.0, true)]);
+	}
+
+	#[test]
+	fn layout_places_the_first_passenger() {
+		let game = with_layout(((2, 3), (9, 9)));
+		assert_eq!((game.passenger_position(), game.goal_position()), ((2, 3), (9, 9)));
+	}
+
+	#[test]
+	#[should_panic(expected = "isn't on the road")]
+	fn layout_on_a_wall_is_rejected() {
+		with_layout(((0, 3), (9, 9)));
+	}
+
+	#[test]
+	#[should_panic(expected = "isn't on the road")]
+	fn layout_off_the_map_is_rejected() {
+		with_layout(((2, 3), (9, 11)));
+	}
 }
@@ -30,5 +30,9 @@ pub fn main() {
 			write!(stdout, "You won the game!").unwrap();
 			break;
 		}
+		if game.has_lost() {
+			write!(stdout, "You ran out of fuel!").unwrap();
+			break;
+		}
 	}
 }
@@ -0,0 +1,229 @@
+//! Pictures of what an agent would do anywhere on the map.
+//!
+//! A `PolicyMap` puts the first taxi on every cell of a game, once with the first passenger still waiting and
+//! once with them in the taxi, and asks a policy which way it would go and how good it thinks the state is.
+//! The answers are drawn as arrows on top of a heatmap of the values, which makes it easy to spot the cells
+//! where an agent turns around and goes back and forth.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use ansi_term::Colour::{self, Black, Green, White, Yellow};
+
+use {Dir, Game, Object, RideState};
+
+/// What the policy says about a cell, `None` for walls
+type Cell = Option<(Dir, f64)>;
+
+/// The greedy action and value of a policy on every cell of the map, before and after the pickup
+#[derive(Clone, Debug)]
+pub struct PolicyMap {
+	rows: usize,
+	cols: usize,
+	pickup: (u32, u32),
+	destination: (u32, u32),
+	/// Row by row, the cells with the passenger still waiting
+	before: Vec<Cell>,
+	/// Row by row, the cells with the passenger in the taxi
+	after: Vec<Cell>,
+	/// The lowest and highest value on either panel
+	range: (f64, f64),
+}
+
+impl PolicyMap {
+	/// Asks `policy` about every cell of `game`. The policy gets a copy of the game with the first taxi on the cell
+	/// and returns the direction it would go in and the value of the state.
+	pub fn new<F>(game: &Game, mut policy: F) -> PolicyMap where F: FnMut(&Game) -> (Dir, f64) {
+		let (rows, cols) = game.world_size();
+		let ride = game.rides[0];
+		let mut panel = |picked_up: bool| {
+			let mut state = game.clone();
+			state.rides[0].state = if picked_up { RideState::Riding } else { RideState::Waiting };
+			state.rides[0].taxi = if picked_up { Some(0) } else { None };
+			(0..rows * cols).map(|i| {
+				let (r, c) = (i / cols, i % cols);
+				if state.world[r][c] == Object::Wall {
+					return None;
+				}
+				state.taxis[0].position = (r as u32, c as u32);
+				Some(policy(&state))
+			}).collect()
+		};
+		let (before, after): (Vec<Cell>, Vec<Cell>) = (panel(false), panel(true));
+		let range = before.iter().chain(&after).filter_map(|cell| cell.map(|(_, v)| v))
+			.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), v| (low.min(v), high.max(v)));
+		PolicyMap {
+			rows,
+			cols,
+			pickup: ride.pickup,
+			destination: ride.destination,
+			before,
+			after,
+			range,
+		}
+	}
+
+	/// Where a value lies between the lowest and highest value, from 0 to 1
+	fn heat(&self, value: f64) -> f64 {
+		let (low, high) = self.range;
+		if high > low { (value - low) / (high - low) } else { 1.0 }
+	}
+
+	/// Prints both panels side by side in the terminal. Arrows point where the policy would go, the background
+	/// goes from red for the lowest value to green for the highest. The passenger's arrow is yellow and the
+	/// destination's arrow is green.
+	pub fn print(&self) {
+		println!("{:<width$}  After pickup", "Before pickup", width = self.cols * 2);
+		for r in 0..self.rows {
+			for (n, panel) in [&self.before, &self.after].iter().enumerate() {
+				if n > 0 {
+					print!("  ");
+				}
+				for c in 0..self.cols {
+					print!("{}", self.paint_cell(panel[r * self.cols + c], (r as u32, c as u32)));
+				}
+			}
+			println!();
+		}
+		let (low, high) = self.range;
+		println!("Values from {:.2} (red) to {:.2} (green) | {} passenger | {} destination",
+				 low, high, Yellow.paint("\u{2192}"), Green.paint("\u{2192}"));
+	}
+
+	fn paint_cell(&self, cell: Cell, position: (u32, u32)) -> String {
+		match cell {
+			None => White.paint("\u{2588}\u{2588}").to_string(),
+			Some((dir, value)) => {
+				// The 6x6x6 colour cube of 256 colour terminals, from red to green without any blue
+				let heat = self.heat(value);
+				let red = (5.0 * (1.0 - heat)).round() as u8;
+				let green = (5.0 * heat).round() as u8;
+				let background = Colour::Fixed(16 + 36 * red + 6 * green);
+				let foreground = if position == self.pickup {
+					Yellow
+				} else if position == self.destination {
+					Green
+				} else {
+					Black
+				};
+				foreground.on(background).bold().paint(format!("{} ", arrow(dir))).to_string()
+			}
+		}
+	}
+
+	/// The same picture as `print` as an SVG image
+	pub fn to_svg(&self) -> String {
+		const SIZE: usize = 32;
+		const TITLE: usize = 24;
+		let width = self.cols * SIZE;
+		let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+							   font-family=\"sans-serif\" text-anchor=\"middle\">\n",
+							  2 * width + SIZE, self.rows * SIZE + TITLE);
+		for (n, (title, panel)) in [("Before pickup", &self.before), ("After pickup", &self.after)].iter().enumerate() {
+			let x0 = n * (width + SIZE);
+			svg += &format!("<text x=\"{}\" y=\"17\" font-size=\"16\">{}</text>\n", x0 + width / 2, title);
+			for r in 0..self.rows {
+				for c in 0..self.cols {
+					let (x, y) = (x0 + c * SIZE, TITLE + r * SIZE);
+					match panel[r * self.cols + c] {
+						None => svg += &format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#444\"/>\n",
+												x, y, SIZE, SIZE),
+						Some((dir, value)) => {
+							let heat = self.heat(value);
+							let (red, green) = ((255.0 * (1.0 - heat)) as u8, (255.0 * heat) as u8);
+							let outline = if (r as u32, c as u32) == self.pickup {
+								" stroke=\"#d4b000\" stroke-width=\"3\""
+							} else if (r as u32, c as u32) == self.destination {
+								" stroke=\"#008000\" stroke-width=\"3\""
+							} else {
+								""
+							};
+							svg += &format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"rgb({},{},0)\"{}>\
+											 <title>{:.3}</title></rect>\n", x, y, SIZE, SIZE, red, green, outline, value);
+							svg += &format!("<text x=\"{}\" y=\"{}\" font-size=\"20\">{}</text>\n",
+											x + SIZE / 2, y + SIZE / 2 + 7, arrow(dir));
+						}
+					}
+				}
+			}
+		}
+		svg += "</svg>\n";
+		svg
+	}
+
+	/// Writes the SVG image to a file
+	pub fn save_svg<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+		File::create(path)?.write_all(self.to_svg().as_bytes())
+	}
+}
+
+fn arrow(dir: Dir) -> &'static str {
+	match dir {
+		Dir::Up => "\u{2191}",
+		Dir::Right => "\u{2192}",
+		Dir::Down => "\u{2193}",
+		Dir::Left => "\u{2190}",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use GameConfig;
+
+	/// Goes right before the pickup and left after it, the value is the column, negated after the pickup
+	fn map() -> PolicyMap {
+		let game = Game::with_config(GameConfig { seed: Some(1), layout: Some(((1, 2), (1, 3))), ..GameConfig::default() }, false);
+		PolicyMap::new(&game, |state| {
+			let column = f64::from(state.player_position().1);
+			if state.passenger_picked_up() { (Dir::Left, -column) } else { (Dir::Right, column) }
+		})
+	}
+
+	/// The square drawn for a cell of a panel, without its tooltip
+	fn rect(svg: &str, panel: usize, (r, c): (usize, usize)) -> &str {
+		let start = format!("<rect x=\"{}\" y=\"{}\" ", panel * 12 * 32 + c * 32, 24 + r * 32);
+		let line = svg.lines().find(|line| line.starts_with(&start)).expect("Cell isn't drawn");
+		line.split("><title>").next().unwrap()
+	}
+
+	#[test]
+	fn svg_has_an_arrow_on_every_road_cell() {
+		let svg = map().to_svg();
+		assert!(svg.contains(">Before pickup</text>") && svg.contains(">After pickup</text>"));
+		// 81 road cells and 40 walls on each panel
+		assert_eq!(svg.matches(arrow(Dir::Right)).count(), 81);
+		assert_eq!(svg.matches(arrow(Dir::Left)).count(), 81);
+		assert_eq!(svg.matches(arrow(Dir::Up)).count() + svg.matches(arrow(Dir::Down)).count(), 0);
+		assert_eq!(svg.matches("fill=\"#444\"").count(), 80);
+		assert_eq!(rect(&svg, 0, (0, 0)), "<rect x=\"0\" y=\"24\" width=\"32\" height=\"32\" fill=\"#444\"/>");
+	}
+
+	#[test]
+	fn svg_heatmap_spans_both_panels() {
+		let svg = map().to_svg();
+		// Values go from -9 on the right of the second panel to 9 on the right of the first
+		assert_eq!(rect(&svg, 0, (5, 9)), "<rect x=\"288\" y=\"184\" width=\"32\" height=\"32\" fill=\"rgb(0,255,0)\"");
+		assert_eq!(rect(&svg, 1, (5, 9)), "<rect x=\"672\" y=\"184\" width=\"32\" height=\"32\" fill=\"rgb(255,0,0)\"");
+		assert!(rect(&svg, 0, (5, 1)).ends_with("fill=\"rgb(113,141,0)\""), "{}", rect(&svg, 0, (5, 1)));
+		assert!(svg.contains("<title>9.000</title>") && svg.contains("<title>-9.000</title>"));
+	}
+
+	#[test]
+	fn svg_outlines_pickup_and_destination() {
+		let svg = map().to_svg();
+		for panel in 0..2 {
+			assert!(rect(&svg, panel, (1, 2)).ends_with("stroke=\"#d4b000\" stroke-width=\"3\""));
+			assert!(rect(&svg, panel, (1, 3)).ends_with("stroke=\"#008000\" stroke-width=\"3\""));
+		}
+		assert_eq!(svg.matches("stroke=").count(), 4);
+	}
+
+	#[test]
+	fn same_values_everywhere_are_all_green() {
+		let game = Game::with_config(GameConfig { seed: Some(1), ..GameConfig::default() }, false);
+		let svg = PolicyMap::new(&game, |_| (Dir::Up, 0.5)).to_svg();
+		assert_eq!(svg.matches("fill=\"rgb(0,255,0)\"").count(), 162);
+	}
+}
@@ -0,0 +1,150 @@
+//! Ways to show a game while it's being played.
+//!
+//! `Terminal` draws the map in colour and redraws it in place, `PlainText` writes every frame as plain
+//! characters to anything that implements `Write`, which works in logs and when the output isn't a terminal,
+//! and `NoRender` doesn't show anything at all, for when only the result of a game matters.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use ansi_term::Colour::{Black, Cyan};
+use termion::{clear, cursor};
+
+use {Game, Object, RideState};
+
+/// Something that can show a game
+pub trait Renderer {
+	/// Shows the current state of the game, replacing the last frame if the renderer can
+	fn render(&mut self, game: &Game);
+}
+
+/// Draws the map in colour on the terminal, clearing the screen first
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Terminal {
+	/// Printed under the map, after the status line
+	pub footer: String,
+}
+
+impl Terminal {
+	pub fn new() -> Terminal {
+		Terminal { footer: "Press <ESC> to exit game.".to_string() }
+	}
+}
+
+impl Default for Terminal {
+	fn default() -> Terminal {
+		Terminal::new()
+	}
+}
+
+impl Renderer for Terminal {
+	/// Waiting passengers are full blocks, destinations are shaded blocks in the color of their passenger.
+	/// With several taxis each one is shown with its index.
+	/// Lines end in `\n\r` so the map also comes out right when the terminal is in raw mode.
+	fn render(&mut self, game: &Game) {
+		let mut out = io::stdout();
+		write!(out, "{}{}", clear::All, cursor::Goto(1, 1)).unwrap();
+		let destination_marker = if game.config.rides.passengers == 1 { "\u{2588}" } else { "\u{2592}" };
+		let mut overlay = HashMap::new();
+		// Destinations go first so that waiting passengers are drawn on top of them
+		for (i, ride) in game.rides.iter().enumerate() {
+			if ride.state != RideState::Delivered {
+				overlay.insert(ride.destination, game.ride_colours(i).1.paint(destination_marker.to_string()));
+			}
+		}
+		for (i, ride) in game.rides.iter().enumerate() {
+			if ride.state == RideState::Waiting {
+				overlay.insert(ride.pickup, game.ride_colours(i).0.paint("\u{2588}".to_string()));
+			}
+		}
+		for (i, taxi) in game.taxis.iter().enumerate() {
+			let marker = if game.taxis.len() == 1 {
+				Cyan.paint("\u{2588}".to_string())
+			} else {
+				Black.on(Cyan).paint(i.to_string())
+			};
+			overlay.insert(taxi.position, marker);
+		}
+		for r in 0..Game::WORLD_HEIGHT {
+			for c in 0..Game::WORLD_WIDTH {
+				match overlay.get(&(r as u32, c as u32)) {
+					Some(marker) => write!(out, "{}", marker).unwrap(),
+					None => write!(out, "{}", game.world[r][c]).unwrap(),
+				}
+			}
+			write!(out, "\n\r").unwrap();
+		}
+		write!(out, "\n\r{}\n\r\n\r{}\n\r", game.status(), self.footer).unwrap();
+		out.flush().unwrap();
+	}
+}
+
+/// Writes every frame as plain characters, one after the other.
+/// Walls are `#`, fuel stations `F`, empty cells `.`, waiting passengers `P`, destinations `D` and taxis `T`,
+/// or their index when there's more than one. With several passengers, passengers and destinations are
+/// shown with the letters `a`, `b`, ... and `A`, `B`, ... instead, so they can be told apart.
+pub struct PlainText<W: Write> {
+	out: W,
+}
+
+impl<W: Write> PlainText<W> {
+	pub fn new(out: W) -> PlainText<W> {
+		PlainText { out }
+	}
+
+	/// Gives back what the frames were written to
+	pub fn into_inner(self) -> W {
+		self.out
+	}
+
+	/// The map as lines of characters
+	pub fn frame(game: &Game) -> Vec<String> {
+		let single = game.config.rides.passengers == 1;
+		let mut map = game.world.iter().map(|row| row.iter().map(|object| match *object {
+			Object::Wall => '#',
+			Object::FuelStation => 'F',
+			Object::Empty => '.',
+		}).collect::<Vec<_>>()).collect::<Vec<_>>();
+		let letter = |base: u8, i: usize| (base + (i % 26) as u8) as char;
+		for (i, ride) in game.rides.iter().enumerate() {
+			if ride.state != RideState::Delivered {
+				let (r, c) = ride.destination;
+				map[r as usize][c as usize] = if single { 'D' } else { letter(b'A', i) };
+			}
+		}
+		for (i, ride) in game.rides.iter().enumerate() {
+			if ride.state == RideState::Waiting {
+				let (r, c) = ride.pickup;
+				map[r as usize][c as usize] = if single { 'P' } else { letter(b'a', i) };
+			}
+		}
+		for (i, taxi) in game.taxis.iter().enumerate() {
+			let (r, c) = taxi.position;
+			map[r as usize][c as usize] = if game.taxis.len() == 1 {
+				'T'
+			} else {
+				std::char::from_digit(i as u32 % 10, 10).unwrap()
+			};
+		}
+		map.into_iter().map(|row| row.into_iter().collect()).collect()
+	}
+}
+
+impl<W: Write> Renderer for PlainText<W> {
+	fn render(&mut self, game: &Game) {
+		for line in PlainText::<W>::frame(game) {
+			writeln!(self.out, "{}", line).unwrap();
+		}
+		writeln!(self.out, "{}", game.status()).unwrap();
+		writeln!(self.out).unwrap();
+		self.out.flush().unwrap();
+	}
+}
+
+/// Doesn't show anything
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NoRender;
+
+impl Renderer for NoRender {
+	fn render(&mut self, _game: &Game) {}
+}
@@ -0,0 +1,178 @@
+//! Reward functions for learning to play the taxi game.
+//!
+//! A reward function looks at the game before and after a move and decides how good that move was
+//! for one of the taxis. Pass one to `Game::step` or `Game::step_all` to get rewards straight from the game.
+
+use {Game, RideState};
+
+static DELIVERY_REWARD: f64 = 20.0;
+
+/// Decides the reward a taxi gets for a move
+pub trait RewardFunction {
+	/// Reward for the taxi with index `taxi` for the move that turned `before` into `after`
+	fn reward(&self, before: &Game, after: &Game, taxi: usize) -> f64;
+}
+
+impl<F> RewardFunction for F where F: Fn(&Game, &Game, usize) -> f64 {
+	fn reward(&self, before: &Game, after: &Game, taxi: usize) -> f64 {
+		self(before, after, taxi)
+	}
+}
+
+/// Gives 1 for the move that wins the game and nothing otherwise
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SparseWin;
+
+impl RewardFunction for SparseWin {
+	fn reward(&self, before: &Game, after: &Game, _: usize) -> f64 {
+		if !before.has_won() && after.has_won() { 1.0 } else { 0.0 }
+	}
+}
+
+/// Costs `penalty` for every move that doesn't win the game, the winning move gives `win`
+#[derive(Clone, Copy, Debug)]
+pub struct StepPenalty {
+	pub penalty: f64,
+	pub win: f64,
+}
+
+impl Default for StepPenalty {
+	fn default() -> StepPenalty {
+		StepPenalty {
+			penalty: 1.0,
+			win: 10.0,
+		}
+	}
+}
+
+impl RewardFunction for StepPenalty {
+	fn reward(&self, before: &Game, after: &Game, _: usize) -> f64 {
+		if !before.has_won() && after.has_won() { self.win } else { -self.penalty }
+	}
+}
+
+/// The rewards of the Taxi-v3 environment from OpenAI Gym: -1 for every move and 20 for every passenger delivered.
+/// Picking up and dropping off passengers happens automatically here, so there's no -10 for doing that wrong.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ClassicTaxi;
+
+impl RewardFunction for ClassicTaxi {
+	fn reward(&self, before: &Game, after: &Game, taxi: usize) -> f64 {
+		let delivered = after.delivered_by(taxi) - before.delivered_by(taxi);
+		DELIVERY_REWARD * delivered as f64 - 1.0
+	}
+}
+
+/// Adds potential-based shaping on top of another reward function.
+/// The potential of a game is minus the driving distance (around walls) from the taxi to the nearest place it should
+/// go to next, a waiting passenger or the destination of someone riding along. Moving closer then gives a bit of
+/// extra reward and moving away costs a bit. Because the shaping is `discount * potential(after) - potential(before)`
+/// it doesn't change which policy is optimal, as long as `discount` is the one used for learning.
+#[derive(Clone, Copy, Debug)]
+pub struct PotentialShaping<R> {
+	pub base: R,
+	pub discount: f64,
+}
+
+impl<R> PotentialShaping<R> {
+	/// Shape `base` for an agent that learns with the given discount factor
+	pub fn new(base: R, discount: f64) -> PotentialShaping<R> {
+		PotentialShaping {
+			base,
+			discount,
+		}
+	}
+
+	/// Potential of the game for the taxi with index `taxi`, 0 once the game is over
+	pub fn potential(game: &Game, taxi: usize) -> f64 {
+		if game.is_over() {
+			return 0.0;
+		}
+		let position = game.taxi_position(taxi);
+		game.rides().iter()
+			.filter_map(|r| match r.state {
+				RideState::Waiting => Some(r.pickup),
+				RideState::Riding if r.taxi == Some(taxi) => Some(r.destination),
+				_ => None,
+			})
+			.filter_map(|target| game.travel_distance(position, target))
+			.min()
+			.map_or(0.0, |d| -(d as f64))
+	}
+}
+
+impl<R: RewardFunction> RewardFunction for PotentialShaping<R> {
+	fn reward(&self, before: &Game, after: &Game, taxi: usize) -> f64 {
+		let shaping = self.discount * Self::potential(after, taxi) - Self::potential(before, taxi);
+		self.base.reward(before, after, taxi) + shaping
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use {Dir, GameConfig};
+
+	/// Bumps into a wall, makes an ordinary move there and back, picks up the passenger and wins by dropping them off
+	const MOVES: [Dir; 5] = [Dir::Up, Dir::Down, Dir::Up, Dir::Right, Dir::Right];
+
+	fn game() -> Game {
+		Game::with_config(GameConfig { seed: Some(1), layout: Some(((1, 2), (1, 3))), ..GameConfig::default() }, false)
+	}
+
+	fn rewards<R: RewardFunction>(reward: &R) -> Vec<f64> {
+		let mut game = game();
+		MOVES.iter().map(|&dir| game.step(dir, reward)).collect()
+	}
+
+	#[test]
+	fn sparse_win_only_rewards_winning() {
+		assert_eq!(rewards(&SparseWin), vec![0.0, 0.0, 0.0, 0.0, 1.0]);
+	}
+
+	#[test]
+	fn step_penalty_costs_every_move_but_the_winning_one() {
+		assert_eq!(rewards(&StepPenalty::default()), vec![-1.0, -1.0, -1.0, -1.0, 10.0]);
+		assert_eq!(rewards(&StepPenalty { penalty: 0.5, win: 3.0 }), vec![-0.5, -0.5, -0.5, -0.5, 3.0]);
+	}
+
+	#[test]
+	fn classic_taxi_pays_for_deliveries() {
+		assert_eq!(rewards(&ClassicTaxi), vec![-1.0, -1.0, -1.0, -1.0, 19.0]);
+	}
+
+	#[test]
+	fn potential_is_minus_the_distance_to_go() {
+		let mut game = game();
+		let mut potentials = vec![PotentialShaping::<ClassicTaxi>::potential(&game, 0)];
+		for &dir in MOVES.iter() {
+			game.make_move(dir);
+			potentials.push(PotentialShaping::<ClassicTaxi>::potential(&game, 0));
+		}
+		assert_eq!(potentials, vec![-1.0, -1.0, -2.0, -1.0, -1.0, 0.0]);
+	}
+
+	#[test]
+	fn shaping_adds_the_discounted_potential_difference() {
+		let shaped = PotentialShaping::new(ClassicTaxi, 0.9);
+		let mut game = game();
+		for &dir in MOVES.iter() {
+			let before = game.clone();
+			let reward = game.step(dir, &shaped);
+			let potential = |g: &Game| PotentialShaping::<ClassicTaxi>::potential(g, 0);
+			let expected = ClassicTaxi.reward(&before, &game, 0) + 0.9 * potential(&game) - potential(&before);
+			assert!((reward - expected).abs() < 1e-9, "{} != {} after {:?}", reward, expected, dir);
+		}
+		assert!(game.has_won());
+		let shaped = rewards(&shaped);
+		assert!((shaped[4] - 20.0).abs() < 1e-9, "Winning move got {}", shaped[4]);
+	}
+
+	#[test]
+	fn closures_are_reward_functions() {
+		let delivered = |_: &Game, after: &Game, taxi: usize| f64::from(after.delivered_by(taxi)) * 5.0;
+		assert_eq!(rewards(&delivered), vec![0.0, 0.0, 0.0, 0.0, 5.0]);
+		let mut game = game();
+		assert_eq!(game.step(Dir::Right, &|before: &Game, _: &Game, _: usize| f64::from(before.moves)), 0.0);
+	}
+}